@@ -0,0 +1,737 @@
+//! A `Map` that searches a small inline array until it grows big enough to want a hash map.
+
+use core::any::TypeId;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::mem;
+use core::slice;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+use crate::storage::{
+    DefaultStorage, RawCapacity, RawEntry, RawOccupiedEntry, RawStorage, RawVacantEntry,
+};
+
+/// How many items a [`RawMap`] keeps inline, found by linear search, before it moves them into a
+/// hash map.
+pub const THRESHOLD: usize = 8;
+
+/// The hash map, with [`TypeIdHasher`](crate::TypeIdHasher), that a [`RawMap`] moves its items
+/// into when there are too many for the inline array.
+type Large<A> = DefaultStorage<A>;
+
+/// Items kept inline, found by linear search. The first `len` slots of each array are in use,
+/// and the rest of `values` are `None`.
+struct Small<A: ?Sized> {
+    len: usize,
+    type_ids: [TypeId; THRESHOLD],
+    values: [Option<Box<A>>; THRESHOLD],
+}
+
+impl<A: ?Sized> Small<A> {
+    #[inline]
+    fn new() -> Small<A> {
+        Small {
+            len: 0,
+            type_ids: [TypeId::of::<()>(); THRESHOLD],
+            values: core::array::from_fn(|_| None),
+        }
+    }
+
+    #[inline]
+    fn position(&self, type_id: TypeId) -> Option<usize> {
+        self.type_ids[..self.len].iter().position(|&probe| probe == type_id)
+    }
+
+    #[inline]
+    fn value(&self, index: usize) -> &A {
+        match self.values[index] {
+            Some(ref value) => value,
+            None => unreachable!("no value in a slot in use"),
+        }
+    }
+
+    #[inline]
+    fn value_mut(&mut self, index: usize) -> &mut Box<A> {
+        match self.values[index] {
+            Some(ref mut value) => value,
+            None => unreachable!("no value in a slot in use"),
+        }
+    }
+
+    /// Adds an item, which mustn’t be there already; there must be room for it.
+    #[inline]
+    fn push(&mut self, type_id: TypeId, value: Box<A>) -> &mut Box<A> {
+        let index = self.len;
+        self.type_ids[index] = type_id;
+        self.len += 1;
+        self.values[index].insert(value)
+    }
+
+    /// Removes the item at `index`, moving the last item into its place.
+    #[inline]
+    fn swap_remove(&mut self, index: usize) -> Box<A> {
+        self.len -= 1;
+        self.type_ids.swap(index, self.len);
+        self.values.swap(index, self.len);
+        match self.values[self.len].take() {
+            Some(value) => value,
+            None => unreachable!("no value in a slot in use"),
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        for value in &mut self.values[..self.len] {
+            *value = None;
+        }
+        self.len = 0;
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for Small<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> Small<A> {
+        Small {
+            len: self.len,
+            type_ids: self.type_ids,
+            values: self.values.clone(),
+        }
+    }
+}
+
+enum Repr<A: ?Sized> {
+    Small(Small<A>),
+    Large(Large<A>),
+}
+
+/// The storage behind an adaptive [`Map`]: up to [`THRESHOLD`] items in an inline array, searched
+/// linearly, and any more in a `HashMap` with [`TypeIdHasher`](crate::TypeIdHasher).
+pub struct RawMap<A: ?Sized> {
+    repr: Repr<A>,
+}
+
+map_aliases! {
+    /// A collection containing zero or one values for any given type and allowing convenient,
+    /// type-safe access to those values, searching a small inline array while it holds only a
+    /// few of them.
+    ///
+    /// This is <code>anymap::[Map](crate::Map)&lt;A, [RawMap]&lt;A&gt;&gt;</code>, which keeps
+    /// up to [`THRESHOLD`] items inline, comparing `TypeId`s one by one to find them, which for
+    /// so few beats hashing; it needs no allocation beyond the values’ boxes. When an item is
+    /// added past that, it moves them all into a `HashMap`, as the default `Map` uses, and stays
+    /// there, even if items are removed, until [`shrink_to_fit`](crate::Map::shrink_to_fit)
+    /// finds few enough to move back. It has the same API as the other `Map`s, and works with
+    /// the same value types (`Any`, [`CloneAny`](crate::CloneAny),
+    /// [`DebugAny`](crate::DebugAny), *&c.*, with `+ Send` and/or `+ Sync`).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut data = anymap::adaptive::AnyMap::new();
+    /// data.insert(42i32);
+    /// data.insert("forty-two");
+    /// assert_eq!(data.get(), Some(&42i32));
+    /// assert!(data.as_raw().is_inline());
+    /// ```
+    RawMap
+}
+
+impl<A: ?Sized> RawMap<A> {
+    /// Creates empty storage, with the items inline.
+    #[inline]
+    pub fn new() -> RawMap<A> {
+        RawMap {
+            repr: Repr::Small(Small::new()),
+        }
+    }
+
+    /// Returns true if the items are kept inline, rather than in a hash map.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.repr, Repr::Small(_))
+    }
+
+    /// Moves the items into a hash map with space for at least `capacity`, unless they’re
+    /// there already.
+    fn grow(&mut self, capacity: usize) -> &mut Large<A> {
+        if let Repr::Small(ref mut small) = self.repr {
+            let small = mem::replace(small, Small::new());
+            let mut large = <Large<A> as RawCapacity<A>>::with_capacity(capacity);
+            for (type_id, value) in (SmallIntoIter { small, index: 0 }) {
+                let _ = large.insert(type_id, value);
+            }
+            self.repr = Repr::Large(large);
+        }
+        match self.repr {
+            Repr::Large(ref mut large) => large,
+            Repr::Small(_) => unreachable!("items not in a hash map after moving them there"),
+        }
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for RawMap<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> RawMap<A> {
+        RawMap {
+            repr: match self.repr {
+                Repr::Small(ref small) => Repr::Small(small.clone()),
+                Repr::Large(ref large) => Repr::Large(large.clone()),
+            },
+        }
+    }
+}
+
+impl<A: ?Sized> Default for RawMap<A> {
+    #[inline]
+    fn default() -> RawMap<A> {
+        RawMap::new()
+    }
+}
+
+impl<A: ?Sized + fmt::Debug> fmt::Debug for RawMap<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<A: ?Sized> IntoIterator for RawMap<A> {
+    type Item = (TypeId, Box<A>);
+    type IntoIter = AdaptiveIntoIter<A>;
+
+    #[inline]
+    fn into_iter(self) -> AdaptiveIntoIter<A> {
+        AdaptiveIntoIter(match self.repr {
+            Repr::Small(small) => IntoIterRepr::Small(SmallIntoIter { small, index: 0 }),
+            Repr::Large(large) => IntoIterRepr::Large(large.into_iter()),
+        })
+    }
+}
+
+unsafe impl<A: ?Sized> RawStorage<A> for RawMap<A> {
+    type Iter<'a> = AdaptiveIter<'a, A> where A: 'a;
+    type IterMut<'a> = AdaptiveIterMut<'a, A> where A: 'a;
+    type Drain<'a> = AdaptiveDrain<'a, A> where A: 'a;
+    type OccupiedEntry<'a> = AdaptiveOccupiedEntry<'a, A> where A: 'a;
+    type VacantEntry<'a> = AdaptiveVacantEntry<'a, A> where A: 'a;
+    type Rebind<B: ?Sized> = RawMap<B>;
+
+    #[inline]
+    fn len(&self) -> usize {
+        match self.repr {
+            Repr::Small(ref small) => small.len,
+            Repr::Large(ref large) => large.len(),
+        }
+    }
+
+    /// Removes all items, keeping the hash map (and its capacity) if it’s in use.
+    #[inline]
+    fn clear(&mut self) {
+        match self.repr {
+            Repr::Small(ref mut small) => small.clear(),
+            Repr::Large(ref mut large) => large.clear(),
+        }
+    }
+
+    #[inline]
+    fn get(&self, type_id: TypeId) -> Option<&A> {
+        match self.repr {
+            Repr::Small(ref small) => small.position(type_id).map(|index| small.value(index)),
+            Repr::Large(ref large) => large.get(&type_id).map(|value| &**value),
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+        match self.repr {
+            Repr::Small(ref mut small) => match small.position(type_id) {
+                Some(index) => Some(&mut **small.value_mut(index)),
+                None => None,
+            },
+            Repr::Large(ref mut large) => large.get_mut(&type_id).map(|value| &mut **value),
+        }
+    }
+
+    #[inline]
+    fn contains_key(&self, type_id: TypeId) -> bool {
+        match self.repr {
+            Repr::Small(ref small) => small.position(type_id).is_some(),
+            Repr::Large(ref large) => large.contains_key(&type_id),
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+        match self.repr {
+            Repr::Small(ref mut small) => match small.position(type_id) {
+                Some(index) => return Some(mem::replace(small.value_mut(index), value)),
+                None if small.len < THRESHOLD => {
+                    let _ = small.push(type_id, value);
+                    return None;
+                },
+                None => (),
+            },
+            Repr::Large(ref mut large) => return large.insert(type_id, value),
+        }
+        self.grow(THRESHOLD * 2).insert(type_id, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        match self.repr {
+            Repr::Small(ref mut small) => {
+                small.position(type_id).map(|index| small.swap_remove(index))
+            },
+            Repr::Large(ref mut large) => large.remove(&type_id),
+        }
+    }
+
+    #[inline]
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+    {
+        if let Repr::Small(ref small) = self.repr {
+            if small.len == THRESHOLD && small.position(type_id).is_none() {
+                let _ = self.grow(THRESHOLD * 2);
+            }
+        }
+        match self.repr {
+            Repr::Small(ref mut small) => match small.position(type_id) {
+                Some(index) => RawEntry::Occupied(AdaptiveOccupiedEntry(
+                    OccupiedRepr::Small { small, index },
+                )),
+                None => RawEntry::Vacant(AdaptiveVacantEntry(VacantRepr::Small { small, type_id })),
+            },
+            Repr::Large(ref mut large) => match RawStorage::entry(large, type_id) {
+                RawEntry::Occupied(e) => RawEntry::Occupied(AdaptiveOccupiedEntry(
+                    OccupiedRepr::Large(e),
+                )),
+                RawEntry::Vacant(e) => RawEntry::Vacant(AdaptiveVacantEntry(VacantRepr::Large(e))),
+            },
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        AdaptiveIter(match self.repr {
+            Repr::Small(ref small) => IterRepr::Small {
+                type_ids: small.type_ids[..small.len].iter(),
+                values: small.values[..small.len].iter(),
+            },
+            Repr::Large(ref large) => IterRepr::Large(large.iter()),
+        })
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        AdaptiveIterMut(match self.repr {
+            Repr::Small(ref mut small) => IterMutRepr::Small {
+                type_ids: small.type_ids[..small.len].iter(),
+                values: small.values[..small.len].iter_mut(),
+            },
+            Repr::Large(ref mut large) => IterMutRepr::Large(large.iter_mut()),
+        })
+    }
+
+    #[inline]
+    fn drain(&mut self) -> Self::Drain<'_> {
+        AdaptiveDrain(match self.repr {
+            Repr::Small(ref mut small) => DrainRepr::Small(
+                SmallIntoIter { small: mem::replace(small, Small::new()), index: 0 },
+                PhantomData,
+            ),
+            Repr::Large(ref mut large) => DrainRepr::Large(large.drain()),
+        })
+    }
+
+    #[inline]
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        match self.repr {
+            Repr::Small(ref mut small) => {
+                let mut index = 0;
+                while index < small.len {
+                    if f(small.type_ids[index], small.value_mut(index)) {
+                        index += 1;
+                    } else {
+                        drop(small.swap_remove(index));
+                    }
+                }
+            },
+            Repr::Large(ref mut large) => large.retain(|&type_id, value| f(type_id, &mut **value)),
+        }
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        match self.repr {
+            Repr::Small(ref small) => {
+                let capacity = small.len.checked_add(additional).expect("capacity overflow");
+                if capacity > THRESHOLD {
+                    let _ = self.grow(capacity);
+                }
+            },
+            Repr::Large(ref mut large) => large.reserve(additional),
+        }
+    }
+}
+
+impl<A: ?Sized> RawCapacity<A> for RawMap<A> {
+    /// Creates empty storage, inline if `capacity` is no more than [`THRESHOLD`].
+    #[inline]
+    fn with_capacity(capacity: usize) -> RawMap<A> {
+        RawMap {
+            repr: if capacity <= THRESHOLD {
+                Repr::Small(Small::new())
+            } else {
+                Repr::Large(<Large<A> as RawCapacity<A>>::with_capacity(capacity))
+            },
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        match self.repr {
+            Repr::Small(_) => THRESHOLD,
+            Repr::Large(ref large) => large.capacity(),
+        }
+    }
+
+    /// Shrinks the hash map as much as possible, or moves the items back inline if there are
+    /// few enough.
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        if let Repr::Large(ref mut large) = self.repr {
+            if large.len() <= THRESHOLD {
+                let mut small = Small::new();
+                for (type_id, value) in large.drain() {
+                    let _ = small.push(type_id, value);
+                }
+                self.repr = Repr::Small(small);
+            } else {
+                large.shrink_to_fit()
+            }
+        }
+    }
+}
+
+/// A view into an occupied place in an adaptive [`RawMap`].
+pub struct AdaptiveOccupiedEntry<'a, A: ?Sized + 'a>(OccupiedRepr<'a, A>);
+
+enum OccupiedRepr<'a, A: ?Sized + 'a> {
+    Small { small: &'a mut Small<A>, index: usize },
+    Large(<Large<A> as RawStorage<A>>::OccupiedEntry<'a>),
+}
+
+/// A view into a vacant place in an adaptive [`RawMap`].
+pub struct AdaptiveVacantEntry<'a, A: ?Sized + 'a>(VacantRepr<'a, A>);
+
+enum VacantRepr<'a, A: ?Sized + 'a> {
+    /// Somewhere with room, since `entry` moves a full array into a hash map.
+    Small { small: &'a mut Small<A>, type_id: TypeId },
+    Large(<Large<A> as RawStorage<A>>::VacantEntry<'a>),
+}
+
+impl<'a, A: ?Sized> RawOccupiedEntry<'a, A> for AdaptiveOccupiedEntry<'a, A> {
+    #[inline]
+    fn get(&self) -> &A {
+        match self.0 {
+            OccupiedRepr::Small { ref small, index } => small.value(index),
+            OccupiedRepr::Large(ref e) => e.get(),
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut A {
+        match self.0 {
+            OccupiedRepr::Small { ref mut small, index } => small.value_mut(index),
+            OccupiedRepr::Large(ref mut e) => e.get_mut(),
+        }
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut A {
+        match self.0 {
+            OccupiedRepr::Small { small, index } => small.value_mut(index),
+            OccupiedRepr::Large(e) => e.into_mut(),
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, value: Box<A>) -> Box<A> {
+        match self.0 {
+            OccupiedRepr::Small { ref mut small, index } => {
+                mem::replace(small.value_mut(index), value)
+            },
+            OccupiedRepr::Large(ref mut e) => e.insert(value),
+        }
+    }
+
+    #[inline]
+    fn remove(self) -> Box<A> {
+        match self.0 {
+            OccupiedRepr::Small { small, index } => small.swap_remove(index),
+            OccupiedRepr::Large(e) => e.remove(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> RawVacantEntry<'a, A> for AdaptiveVacantEntry<'a, A> {
+    #[inline]
+    fn insert(self, value: Box<A>) -> &'a mut A {
+        match self.0 {
+            VacantRepr::Small { small, type_id } => small.push(type_id, value),
+            VacantRepr::Large(e) => e.insert(value),
+        }
+    }
+}
+
+/// An iterator over the items of an adaptive [`RawMap`].
+pub struct AdaptiveIter<'a, A: ?Sized + 'a>(IterRepr<'a, A>);
+
+enum IterRepr<'a, A: ?Sized + 'a> {
+    Small {
+        type_ids: slice::Iter<'a, TypeId>,
+        values: slice::Iter<'a, Option<Box<A>>>,
+    },
+    Large(<Large<A> as RawStorage<A>>::Iter<'a>),
+}
+
+impl<'a, A: ?Sized> Clone for AdaptiveIter<'a, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        AdaptiveIter(match self.0 {
+            IterRepr::Small { ref type_ids, ref values } => IterRepr::Small {
+                type_ids: type_ids.clone(),
+                values: values.clone(),
+            },
+            IterRepr::Large(ref inner) => IterRepr::Large(inner.clone()),
+        })
+    }
+}
+
+impl<'a, A: ?Sized> Iterator for AdaptiveIter<'a, A> {
+    type Item = (&'a TypeId, &'a Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a Box<A>)> {
+        match self.0 {
+            IterRepr::Small { ref mut type_ids, ref mut values } => {
+                Some((type_ids.next()?, values.next()?.as_ref()?))
+            },
+            IterRepr::Large(ref mut inner) => inner.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            IterRepr::Small { ref type_ids, .. } => type_ids.size_hint(),
+            IterRepr::Large(ref inner) => inner.size_hint(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for AdaptiveIter<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for AdaptiveIter<'a, A> {}
+
+/// A mutable iterator over the items of an adaptive [`RawMap`].
+pub struct AdaptiveIterMut<'a, A: ?Sized + 'a>(IterMutRepr<'a, A>);
+
+enum IterMutRepr<'a, A: ?Sized + 'a> {
+    Small {
+        type_ids: slice::Iter<'a, TypeId>,
+        values: slice::IterMut<'a, Option<Box<A>>>,
+    },
+    Large(<Large<A> as RawStorage<A>>::IterMut<'a>),
+}
+
+impl<'a, A: ?Sized> Iterator for AdaptiveIterMut<'a, A> {
+    type Item = (&'a TypeId, &'a mut Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a mut Box<A>)> {
+        match self.0 {
+            IterMutRepr::Small { ref mut type_ids, ref mut values } => {
+                Some((type_ids.next()?, values.next()?.as_mut()?))
+            },
+            IterMutRepr::Large(ref mut inner) => inner.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            IterMutRepr::Small { ref type_ids, .. } => type_ids.size_hint(),
+            IterMutRepr::Large(ref inner) => inner.size_hint(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for AdaptiveIterMut<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for AdaptiveIterMut<'a, A> {}
+
+/// Takes the items out of a `Small`, for `into_iter` and `drain`.
+struct SmallIntoIter<A: ?Sized> {
+    small: Small<A>,
+    index: usize,
+}
+
+impl<A: ?Sized> Iterator for SmallIntoIter<A> {
+    type Item = (TypeId, Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, Box<A>)> {
+        if self.index == self.small.len {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        Some((self.small.type_ids[index], self.small.values[index].take()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.small.len - self.index;
+        (len, Some(len))
+    }
+}
+
+/// An owning iterator over the items of an adaptive [`RawMap`].
+pub struct AdaptiveIntoIter<A: ?Sized>(IntoIterRepr<A>);
+
+enum IntoIterRepr<A: ?Sized> {
+    Small(SmallIntoIter<A>),
+    Large(<Large<A> as IntoIterator>::IntoIter),
+}
+
+impl<A: ?Sized> Iterator for AdaptiveIntoIter<A> {
+    type Item = (TypeId, Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, Box<A>)> {
+        match self.0 {
+            IntoIterRepr::Small(ref mut inner) => inner.next(),
+            IntoIterRepr::Large(ref mut inner) => inner.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            IntoIterRepr::Small(ref inner) => inner.size_hint(),
+            IntoIterRepr::Large(ref inner) => inner.size_hint(),
+        }
+    }
+}
+
+impl<A: ?Sized> ExactSizeIterator for AdaptiveIntoIter<A> {}
+impl<A: ?Sized> FusedIterator for AdaptiveIntoIter<A> {}
+
+/// A draining iterator over the items of an adaptive [`RawMap`].
+pub struct AdaptiveDrain<'a, A: ?Sized + 'a>(DrainRepr<'a, A>);
+
+enum DrainRepr<'a, A: ?Sized + 'a> {
+    /// The items, already taken out of the map, which is left inline and empty.
+    Small(SmallIntoIter<A>, PhantomData<&'a mut RawMap<A>>),
+    Large(<Large<A> as RawStorage<A>>::Drain<'a>),
+}
+
+impl<'a, A: ?Sized> Iterator for AdaptiveDrain<'a, A> {
+    type Item = (TypeId, Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, Box<A>)> {
+        match self.0 {
+            DrainRepr::Small(ref mut inner, _) => inner.next(),
+            DrainRepr::Large(ref mut inner) => inner.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            DrainRepr::Small(ref inner, _) => inner.size_hint(),
+            DrainRepr::Large(ref inner) => inner.size_hint(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for AdaptiveDrain<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for AdaptiveDrain<'a, A> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::CloneDebugAny;
+    use super::*;
+
+    macro_rules! types {
+        ($($T:ident)*) => {
+            $(#[derive(Clone, Debug, PartialEq)] struct $T(usize);)*
+
+            fn insert_all(map: &mut Map<dyn CloneDebugAny>) {
+                let mut n = 0;
+                $(
+                    n += 1;
+                    assert_eq!(map.insert($T(n)), None);
+                )*
+            }
+
+            fn check_all(map: &Map<dyn CloneDebugAny>) {
+                let mut n = 0;
+                $(
+                    n += 1;
+                    assert_eq!(map.get::<$T>(), Some(&$T(n)));
+                )*
+            }
+        };
+    }
+
+    types!(T1 T2 T3 T4 T5 T6 T7 T8 T9 T10);
+
+    #[test]
+    fn test_threshold() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        insert_all(&mut map);
+        assert!(!map.as_raw().is_inline());
+        assert_eq!(map.len(), 10);
+        check_all(&map);
+        let clone = map.clone();
+        check_all(&clone);
+        assert_eq!(map.remove::<T10>(), Some(T10(10)));
+        assert_eq!(map.remove::<T9>(), Some(T9(9)));
+        assert!(!map.as_raw().is_inline());
+        map.shrink_to_fit();
+        assert!(map.as_raw().is_inline());
+        assert_eq!(map.len(), 8);
+        assert_eq!(map.capacity(), THRESHOLD);
+        assert_eq!(map.get::<T8>(), Some(&T8(8)));
+
+        // A full array moves into a hash map on the way to a vacant entry.
+        assert_eq!(*map.entry::<T9>().or_insert(T9(90)), T9(90));
+        assert!(!map.as_raw().is_inline());
+        assert_eq!(map.drain().count(), 9);
+        map.shrink_to_fit();
+        assert!(map.as_raw().is_inline());
+
+        let mut map: Map<dyn CloneDebugAny> = Map::with_capacity(THRESHOLD + 1);
+        assert!(!map.as_raw().is_inline());
+        let _ = map.insert(T1(1));
+        map.shrink_to_fit();
+        assert!(map.as_raw().is_inline());
+        map.reserve(THRESHOLD);
+        assert!(!map.as_raw().is_inline());
+        assert_eq!(map.get::<T1>(), Some(&T1(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn test_reserve_overflow() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        let _ = map.insert(T1(1));
+        map.reserve(usize::MAX);
+    }
+}
@@ -1,111 +1,111 @@
 use core::fmt;
 use core::any::{Any, TypeId};
-use core::mem;
+use core::hash::{Hash, Hasher};
 #[cfg(not(feature = "std"))]
 use alloc::boxed::Box;
 
-#[doc(hidden)]
-pub trait CloneToAny {
-    /// Clone `self` into a new `Box<dyn CloneAny>` object.
-    fn clone_to_any(&self) -> Box<dyn CloneAny>;
+impl fmt::Debug for dyn CloneAny {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("dyn CloneAny")
+    }
 }
 
-impl<T: Any + Clone> CloneToAny for T {
+impl fmt::Debug for dyn CloneAny + Send {
     #[inline]
-    fn clone_to_any(&self) -> Box<dyn CloneAny> {
-        Box::new(self.clone())
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("dyn CloneAny + Send")
     }
 }
 
-#[doc(hidden)]
-pub trait CloneToAnySend {
-    /// Clone `self` into a new `Box<dyn CloneAny + Send>` object.
-    fn clone_to_any_send(&self) -> Box<dyn CloneAny + Send>;
+impl fmt::Debug for dyn CloneAny + Sync {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("dyn CloneAny + Sync")
+    }
 }
 
-impl<T: Any + Clone + Send> CloneToAnySend for T {
+impl fmt::Debug for dyn CloneAny + Send + Sync {
     #[inline]
-    fn clone_to_any_send(&self) -> Box<dyn CloneAny + Send> {
-        Box::new(self.clone())
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("dyn CloneAny + Send + Sync")
     }
 }
 
 #[doc(hidden)]
-pub trait CloneToAnySendSync {
-    /// Clone `self` into a new `Box<dyn CloneAny + Send + Sync>` object.
-    fn clone_to_any_send_sync(&self) -> Box<dyn CloneAny + Send + Sync>;
+pub trait DynEq {
+    /// Compare `self` to `other`, which is not equal if it’s of a different type.
+    fn dyn_eq(&self, other: &dyn Any) -> bool;
 }
 
-impl<T: Any + Clone + Send + Sync> CloneToAnySendSync for T {
+impl<T: Any + Eq> DynEq for T {
     #[inline]
-    fn clone_to_any_send_sync(&self) -> Box<dyn CloneAny + Send + Sync> {
-        Box::new(self.clone())
+    fn dyn_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<T>() == Some(self)
     }
 }
 
-// Basic implementation for dyn CloneAny
-impl Clone for Box<dyn CloneAny> {
-    #[inline]
-    fn clone(&self) -> Box<dyn CloneAny> {
-        (**self).clone_to_any()
-    }
-}
+macro_rules! impl_eq {
+    ($any_trait:ident $(+ $auto_traits:ident)*) => {
+        impl PartialEq for dyn $any_trait $(+ $auto_traits)* {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                self.dyn_eq(other)
+            }
+        }
 
-// Implementation for dyn CloneAny + Send
-impl Clone for Box<dyn CloneAny + Send> {
-    #[inline]
-    fn clone(&self) -> Box<dyn CloneAny + Send> {
-        // We need to use transmute here because the trait object doesn't directly
-        // implement CloneToAnySend, but the underlying concrete type does
-        unsafe {
-            let type_id = (**self).type_id();
-            let clone_any = (**self).clone_to_any();
-            
-            // This is safe because:
-            // 1. We know the original was Send (it's in a Box<dyn CloneAny + Send>)
-            // 2. The clone has the same concrete type as the original
-            // 3. Therefore the clone is also Send
-            mem::transmute::<Box<dyn CloneAny>, Box<dyn CloneAny + Send>>(clone_any)
+        impl Eq for dyn $any_trait $(+ $auto_traits)* {}
+
+        impl fmt::Debug for dyn $any_trait $(+ $auto_traits)* {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.pad(stringify!(dyn $any_trait $(+ $auto_traits)*))
+            }
         }
     }
 }
 
-// Implementation for dyn CloneAny + Send + Sync  
-impl Clone for Box<dyn CloneAny + Send + Sync> {
-    #[inline]
-    fn clone(&self) -> Box<dyn CloneAny + Send + Sync> {
-        // Same logic as above, but for Send + Sync
-        unsafe {
-            let type_id = (**self).type_id();
-            let clone_any = (**self).clone_to_any();
-            
-            // This is safe because:
-            // 1. We know the original was Send + Sync
-            // 2. The clone has the same concrete type as the original  
-            // 3. Therefore the clone is also Send + Sync
-            mem::transmute::<Box<dyn CloneAny>, Box<dyn CloneAny + Send + Sync>>(clone_any)
-        }
-    }
+#[doc(hidden)]
+pub trait DynHash {
+    /// Feed `self` into the given hasher.
+    fn dyn_hash(&self, state: &mut dyn Hasher);
 }
 
-impl fmt::Debug for dyn CloneAny {
+impl<T: Any + Hash> DynHash for T {
     #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.pad("dyn CloneAny")
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state)
     }
 }
 
-impl fmt::Debug for dyn CloneAny + Send {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.pad("dyn CloneAny + Send")
+macro_rules! impl_hash {
+    ($any_trait:ident $(+ $auto_traits:ident)*) => {
+        impl_eq!($any_trait $(+ $auto_traits)*);
+
+        impl Hash for dyn $any_trait $(+ $auto_traits)* {
+            #[inline]
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.dyn_hash(state)
+            }
+        }
     }
 }
 
-impl fmt::Debug for dyn CloneAny + Send + Sync {
+/// The name of a value’s type, for an `Any`-like trait to carry in its vtable.
+///
+/// This is implemented for every type with no non-`'static` references. `Any`’s own vtable has
+/// nothing but the `TypeId`, so a `Map<dyn Any>` can’t tell you the names of the types in it; a
+/// map whose value type has `Named` as a supertrait can. All of this crate’s other value types
+/// do, and [`impl_any_trait!`](crate::impl_any_trait) explains how to make your own do so too.
+pub trait Named {
+    /// Gets the name of the type of `self`, from [`core::any::type_name`].
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: Any> Named for T {
     #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.pad("dyn CloneAny + Send + Sync")
+    fn type_name(&self) -> &'static str {
+        core::any::type_name::<T>()
     }
 }
 
@@ -117,6 +117,13 @@ pub trait Downcast {
     /// Gets the `TypeId` of `self`.
     fn type_id(&self) -> TypeId;
 
+    /// Gets the name of the type of `self`, if the trait object has it, which it does if the
+    /// trait has [`Named`] as a supertrait.
+    #[inline]
+    fn type_name(&self) -> Option<&'static str> {
+        None
+    }
+
     // Note the bound through these downcast methods is 'static, rather than the inexpressible
     // concept of Self-but-as-a-trait (where Self is `dyn Trait`). This is sufficient, exceeding
     // TypeId's requirements. Sure, you *can* do CloneAny.downcast_unchecked::<NotClone>() and the
@@ -151,19 +158,289 @@ pub trait Downcast {
 }
 
 /// A trait for the conversion of an object into a boxed trait object.
+///
+/// This is implemented for every `T` whose trait object `A` implements [`BoxFrom<T>`].
 pub trait IntoBox<A: ?Sized + Downcast>: Any {
     /// Convert self into the appropriate boxed form.
     fn into_box(self) -> Box<A>;
+
+    /// Convert a pointer to self into a pointer to the trait object, without touching the value.
+    fn as_dyn_ptr(ptr: *mut Self) -> *mut A;
 }
 
-macro_rules! implement {
-    ($any_trait:ident $(+ $auto_traits:ident)*) => {
-        impl Downcast for dyn $any_trait $(+ $auto_traits)* {
+impl<T: Any, A: ?Sized + BoxFrom<T>> IntoBox<A> for T {
+    #[inline]
+    fn into_box(self) -> Box<A> {
+        A::box_from(self)
+    }
+
+    #[inline]
+    fn as_dyn_ptr(ptr: *mut T) -> *mut A {
+        A::ptr_from(ptr)
+    }
+}
+
+/// The conversion of an object into a boxed trait object, from the trait object’s side.
+///
+/// This is [`IntoBox`] turned around, because the orphan rules won’t let other crates write
+/// `impl<T: MyAny> IntoBox<dyn MyAny> for T`, but they will allow
+/// `impl<T: MyAny> BoxFrom<T> for dyn MyAny`. You won’t normally need to implement this yourself,
+/// though: [`impl_any_trait!`](crate::impl_any_trait) does it for you.
+pub trait BoxFrom<T>: Downcast {
+    /// Box `value` up as this trait object.
+    fn box_from(value: T) -> Box<Self>;
+
+    /// Convert a pointer to a `T` into a pointer to this trait object, for values that aren’t
+    /// boxed. This is just an unsizing coercion, `ptr`.
+    fn ptr_from(ptr: *mut T) -> *mut Self;
+}
+
+/// Cloning a trait object in place, into memory of the caller’s choosing rather than a new box.
+///
+/// [`impl_any_trait!`](crate::impl_any_trait) implements this for cloneable trait objects, through
+/// the clone helper trait; it’s what lets an `inline::Map` clone the values
+/// it stores inline without boxing them.
+pub trait CloneInPlace {
+    /// Clone `self` into `dst`.
+    ///
+    /// # Safety
+    ///
+    /// `dst` must be valid for writes of `size_of_val(self)` bytes, and aligned to
+    /// `align_of_val(self)`.
+    unsafe fn clone_in_place(&self, dst: *mut u8);
+}
+
+/// The conversion of a boxed trait object into one for a supertrait, or with fewer auto traits.
+///
+/// This is what lets [`Map::upcast`](crate::Map::upcast) turn, say, a
+/// `Map<dyn CloneAny + Send + Sync>` into a `Map<dyn Any + Send>`.
+pub trait Upcast<B: ?Sized + Downcast>: Downcast {
+    /// Convert the boxed trait object, without moving or reallocating the value.
+    fn upcast(self: Box<Self>) -> Box<B>;
+}
+
+/// Implement the traits needed to use a subtrait of [`Any`] as the value type of a `Map`.
+///
+/// Given an object-safe trait `MyAny` with `Any` as a supertrait, `impl_any_trait!(MyAny)`
+/// implements [`Downcast`] and [`BoxFrom`] for `dyn MyAny`, `dyn MyAny + Send`, `dyn MyAny + Sync`
+/// and `dyn MyAny + Send + Sync`, so that `Map<dyn MyAny>` and friends just work. It also
+/// implements [`Upcast`] from those to each other with fewer auto traits, and to `dyn Any` and its
+/// variants, so that [`Map::upcast`](crate::Map::upcast) can weaken the map’s value type.
+///
+/// If you want the map to know the names of the types in it (for its `Debug` output and
+/// [`Map::type_names`](crate::Map::type_names)), give `MyAny` the supertrait [`Named`] too, and
+/// write `impl_any_trait!(MyAny, named)`.
+///
+/// If you want the map to be cloneable too, write `impl_any_trait!(MyAny, clone: CloneToMyAny)`
+/// and give `MyAny` the supertrait `CloneToMyAny`, which the macro defines in the current module
+/// and implements for all `MyAny + Clone` types. Put the same visibility as `MyAny` has before
+/// `CloneToMyAny` (e.g. `clone: pub CloneToMyAny`). This is how [`CloneAny`] is implemented.
+/// `CloneToMyAny` has `Named` as a supertrait, so the types are named in this case.
+///
+/// This is how you make your own extension of `Any` with extra functionality; for example, this
+/// crate’s [`DebugAny`] is nothing more than this:
+///
+/// ```rust
+/// use core::any::Any;
+/// use core::fmt::Debug;
+/// use anymap::Named;
+///
+/// pub trait DebugAny: Any + Debug + Named {}
+/// impl<T: Any + Debug> DebugAny for T {}
+/// anymap::impl_any_trait!(DebugAny, named);
+/// ```
+///
+/// And here’s a cloneable one:
+///
+/// ```rust
+/// use core::any::Any;
+/// use core::fmt::Debug;
+///
+/// pub trait Component: Any + Debug + CloneToComponent {
+///     fn name(&self) -> &str;
+/// }
+/// anymap::impl_any_trait!(Component, clone: pub CloneToComponent);
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Position(i32, i32);
+///
+/// impl Component for Position {
+///     fn name(&self) -> &str {
+///         "position"
+///     }
+/// }
+///
+/// # #[cfg(feature = "std")] {
+/// let mut map = anymap::Map::<dyn Component>::new();
+/// map.insert(Position(1, 2));
+/// let map2 = map.clone();
+/// assert_eq!(map2.get::<Position>(), Some(&Position(1, 2)));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! impl_any_trait {
+    ($any_trait:ident) => {
+        $crate::impl_any_trait!(@all $any_trait, @implement);
+    };
+
+    ($any_trait:ident, named) => {
+        $crate::impl_any_trait!(@all $any_trait, @implement_named);
+    };
+
+    ($any_trait:ident, clone: $vis:vis $clone_trait:ident) => {
+        $crate::impl_any_trait!($any_trait, named);
+
+        // The auto trait methods are in the vtable even for types that aren’t Send or Sync; they
+        // just can’t be called on them. But on a `dyn $any_trait + Send`, we know the type *is*
+        // Send, so we can call clone_to_any_send and get the right auto traits on the clone
+        // without any unsafe code.
+        #[doc(hidden)]
+        $vis trait $clone_trait: $crate::Named {
+            /// Clone `self` into a new boxed trait object.
+            fn clone_to_any(&self) -> $crate::__private::Box<dyn $any_trait>;
+
+            /// Clone `self` into a new boxed trait object, with `Send`.
+            fn clone_to_any_send(&self) -> $crate::__private::Box<dyn $any_trait + Send>
+                where Self: Send;
+
+            /// Clone `self` into a new boxed trait object, with `Sync`.
+            fn clone_to_any_sync(&self) -> $crate::__private::Box<dyn $any_trait + Sync>
+                where Self: Sync;
+
+            /// Clone `self` into a new boxed trait object, with `Send + Sync`.
+            fn clone_to_any_send_sync(&self) -> $crate::__private::Box<dyn $any_trait + Send + Sync>
+                where Self: Send + Sync;
+
+            /// Clone `self` into `dst`, which must be valid for writes of a `Self`, and aligned.
+            unsafe fn clone_to_ptr(&self, dst: *mut u8);
+        }
+
+        impl<T: $any_trait + Clone> $clone_trait for T {
+            #[inline]
+            fn clone_to_any(&self) -> $crate::__private::Box<dyn $any_trait> {
+                $crate::__private::Box::new(self.clone())
+            }
+
+            #[inline]
+            fn clone_to_any_send(&self) -> $crate::__private::Box<dyn $any_trait + Send>
+                where Self: Send
+            {
+                $crate::__private::Box::new(self.clone())
+            }
+
+            #[inline]
+            fn clone_to_any_sync(&self) -> $crate::__private::Box<dyn $any_trait + Sync>
+                where Self: Sync
+            {
+                $crate::__private::Box::new(self.clone())
+            }
+
+            #[inline]
+            fn clone_to_any_send_sync(&self) -> $crate::__private::Box<dyn $any_trait + Send + Sync>
+                where Self: Send + Sync
+            {
+                $crate::__private::Box::new(self.clone())
+            }
+
+            #[inline]
+            unsafe fn clone_to_ptr(&self, dst: *mut u8) {
+                dst.cast::<T>().write(self.clone())
+            }
+        }
+
+        $crate::impl_any_trait!(@clone $any_trait, $clone_trait::clone_to_any);
+        $crate::impl_any_trait!(@clone $any_trait + Send, $clone_trait::clone_to_any_send);
+        $crate::impl_any_trait!(@clone $any_trait + Sync, $clone_trait::clone_to_any_sync);
+        $crate::impl_any_trait!(@clone $any_trait + Send + Sync,
+                                $clone_trait::clone_to_any_send_sync);
+    };
+
+    (@clone $any_trait:ident $(+ $auto_traits:ident)*, $clone_trait:ident::$method:ident) => {
+        impl Clone for $crate::__private::Box<dyn $any_trait $(+ $auto_traits)*> {
+            #[inline]
+            fn clone(&self) -> $crate::__private::Box<dyn $any_trait $(+ $auto_traits)*> {
+                $clone_trait::$method(&**self)
+            }
+        }
+
+        impl $crate::CloneInPlace for dyn $any_trait $(+ $auto_traits)* {
+            #[inline]
+            unsafe fn clone_in_place(&self, dst: *mut u8) {
+                $clone_trait::clone_to_ptr(self, dst)
+            }
+        }
+    };
+
+    (@all $any_trait:ident, @$implement:ident) => {
+        $crate::impl_any_trait!(@$implement $any_trait);
+        $crate::impl_any_trait!(@$implement $any_trait + Send);
+        $crate::impl_any_trait!(@$implement $any_trait + Sync);
+        $crate::impl_any_trait!(@$implement $any_trait + Send + Sync);
+        $crate::impl_any_trait!(@weaken $any_trait);
+        const _: () = {
+            use $crate::__private::Any;
+            $crate::impl_any_trait!(@upcast $any_trait => Any);
+        };
+    };
+
+    // Dropping auto traits, for a trait to itself.
+    (@weaken $any_trait:ident) => {
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send => $any_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Sync => $any_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $any_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $any_trait + Send);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $any_trait + Sync);
+    };
+
+    // Upcasting to a supertrait, with any of the auto traits maintained or dropped.
+    (@upcast $any_trait:ident => $super_trait:ident) => {
+        $crate::impl_any_trait!(@upcast_one $any_trait => $super_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send => $super_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send => $super_trait + Send);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Sync => $super_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Sync => $super_trait + Sync);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $super_trait);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $super_trait + Send);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $super_trait + Sync);
+        $crate::impl_any_trait!(@upcast_one $any_trait + Send + Sync => $super_trait + Send + Sync);
+    };
+
+    (@upcast_one $any_trait:ident $(+ $auto_traits:ident)* =>
+        $super_trait:ident $(+ $super_auto_traits:ident)*) => {
+        impl $crate::Upcast<dyn $super_trait $(+ $super_auto_traits)*>
+            for dyn $any_trait $(+ $auto_traits)*
+        {
+            #[inline]
+            fn upcast(self: $crate::__private::Box<Self>)
+                -> $crate::__private::Box<dyn $super_trait $(+ $super_auto_traits)*>
+            {
+                self
+            }
+        }
+    };
+
+    (@implement $any_trait:ident $(+ $auto_traits:ident)*) => {
+        $crate::impl_any_trait!(@downcast $any_trait $(+ $auto_traits)* {});
+    };
+
+    (@implement_named $any_trait:ident $(+ $auto_traits:ident)*) => {
+        $crate::impl_any_trait!(@downcast $any_trait $(+ $auto_traits)* {
+            #[inline]
+            fn type_name(&self) -> Option<&'static str> {
+                Some($crate::Named::type_name(self))
+            }
+        });
+    };
+
+    (@downcast $any_trait:ident $(+ $auto_traits:ident)* { $($type_name:tt)* }) => {
+        impl $crate::Downcast for dyn $any_trait $(+ $auto_traits)* {
             #[inline]
-            fn type_id(&self) -> TypeId {
+            fn type_id(&self) -> $crate::__private::TypeId {
                 self.type_id()
             }
 
+            $($type_name)*
+
             #[inline]
             unsafe fn downcast_ref_unchecked<T: 'static>(&self) -> &T {
                 &*(self as *const Self as *const T)
@@ -175,23 +452,32 @@ macro_rules! implement {
             }
 
             #[inline]
-            unsafe fn downcast_unchecked<T: 'static>(self: Box<Self>) -> Box<T> {
-                Box::from_raw(Box::into_raw(self) as *mut T)
+            unsafe fn downcast_unchecked<T: 'static>(self: $crate::__private::Box<Self>)
+                -> $crate::__private::Box<T>
+            {
+                $crate::__private::Box::from_raw($crate::__private::Box::into_raw(self) as *mut T)
             }
         }
 
-        impl<T: $any_trait $(+ $auto_traits)*> IntoBox<dyn $any_trait $(+ $auto_traits)*> for T {
+        impl<T: $any_trait $(+ $auto_traits)*> $crate::BoxFrom<T> for dyn $any_trait $(+ $auto_traits)* {
             #[inline]
-            fn into_box(self) -> Box<dyn $any_trait $(+ $auto_traits)*> {
-                Box::new(self)
+            fn box_from(value: T) -> $crate::__private::Box<dyn $any_trait $(+ $auto_traits)*> {
+                $crate::__private::Box::new(value)
+            }
+
+            #[inline]
+            fn ptr_from(ptr: *mut T) -> *mut Self {
+                ptr
             }
         }
-    }
+    };
 }
 
-implement!(Any);
-implement!(Any + Send);
-implement!(Any + Send + Sync);
+impl_any_trait!(@implement Any);
+impl_any_trait!(@implement Any + Send);
+impl_any_trait!(@implement Any + Sync);
+impl_any_trait!(@implement Any + Send + Sync);
+impl_any_trait!(@weaken Any);
 
 /// [`Any`], but with cloning.
 ///
@@ -200,6 +486,171 @@ implement!(Any + Send + Sync);
 pub trait CloneAny: Any + CloneToAny {}
 impl<T: Any + Clone> CloneAny for T {}
 
-implement!(CloneAny);
-implement!(CloneAny + Send);
-implement!(CloneAny + Send + Sync);
+impl_any_trait!(CloneAny, clone: pub CloneToAny);
+
+/// [`Any`], but with debug formatting.
+///
+/// Every type with no non-`'static` references that implements `Debug` implements `DebugAny`.
+/// Unlike with `Any`, the `Debug` output of a `Map<dyn DebugAny>` shows the values themselves.
+pub trait DebugAny: Any + fmt::Debug + Named {}
+impl<T: Any + fmt::Debug> DebugAny for T {}
+
+impl_any_trait!(DebugAny, named);
+
+/// [`Any`], but with cloning and debug formatting: [`CloneAny`] and [`DebugAny`] combined.
+///
+/// Every type with no non-`'static` references that implements `Clone` and `Debug` implements
+/// `CloneDebugAny`.
+pub trait CloneDebugAny: CloneAny + DebugAny + CloneToDebugAny {}
+impl<T: Any + Clone + fmt::Debug> CloneDebugAny for T {}
+
+impl_any_trait!(CloneDebugAny, clone: pub CloneToDebugAny);
+impl_any_trait!(@upcast CloneDebugAny => CloneAny);
+impl_any_trait!(@upcast CloneDebugAny => DebugAny);
+
+/// [`Any`], but with equality comparison.
+///
+/// Every type with no non-`'static` references that implements `Eq` implements `EqAny`.
+/// A `Map<dyn EqAny>` implements `PartialEq` and `Eq`: two maps are equal if they contain the same
+/// types, and equal values for each of those types.
+pub trait EqAny: Any + DynEq + Named {}
+impl<T: Any + Eq> EqAny for T {}
+
+impl_any_trait!(EqAny, named);
+impl_eq!(EqAny);
+impl_eq!(EqAny + Send);
+impl_eq!(EqAny + Sync);
+impl_eq!(EqAny + Send + Sync);
+
+/// [`Any`], but with cloning and equality comparison: [`CloneAny`] and [`EqAny`] combined.
+///
+/// Every type with no non-`'static` references that implements `Clone` and `Eq` implements
+/// `CloneEqAny`.
+pub trait CloneEqAny: CloneAny + EqAny + CloneToEqAny {}
+impl<T: Any + Clone + Eq> CloneEqAny for T {}
+
+impl_any_trait!(CloneEqAny, clone: pub CloneToEqAny);
+impl_any_trait!(@upcast CloneEqAny => CloneAny);
+impl_any_trait!(@upcast CloneEqAny => EqAny);
+impl_eq!(CloneEqAny);
+impl_eq!(CloneEqAny + Send);
+impl_eq!(CloneEqAny + Sync);
+impl_eq!(CloneEqAny + Send + Sync);
+
+/// [`Any`], but with equality comparison and hashing.
+///
+/// Every type with no non-`'static` references that implements `Eq` and `Hash` implements
+/// `HashAny`. A `Map<dyn HashAny>` implements `Hash` as well as `Eq`, so it can be used as a key in
+/// a hash map.
+pub trait HashAny: EqAny + DynHash {}
+impl<T: Any + Eq + Hash> HashAny for T {}
+
+impl_any_trait!(HashAny, named);
+impl_any_trait!(@upcast HashAny => EqAny);
+impl_hash!(HashAny);
+impl_hash!(HashAny + Send);
+impl_hash!(HashAny + Sync);
+impl_hash!(HashAny + Send + Sync);
+
+/// [`Any`], but with cloning, equality comparison and hashing: [`CloneAny`] and [`HashAny`]
+/// combined.
+///
+/// Every type with no non-`'static` references that implements `Clone`, `Eq` and `Hash`
+/// implements `CloneHashAny`.
+pub trait CloneHashAny: CloneEqAny + HashAny + CloneToHashAny {}
+impl<T: Any + Clone + Eq + Hash> CloneHashAny for T {}
+
+impl_any_trait!(CloneHashAny, clone: pub CloneToHashAny);
+impl_any_trait!(@upcast CloneHashAny => CloneEqAny);
+impl_any_trait!(@upcast CloneHashAny => HashAny);
+impl_any_trait!(@upcast CloneHashAny => CloneAny);
+impl_any_trait!(@upcast CloneHashAny => EqAny);
+impl_hash!(CloneHashAny);
+impl_hash!(CloneHashAny + Send);
+impl_hash!(CloneHashAny + Sync);
+impl_hash!(CloneHashAny + Send + Sync);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{string::String, sync::Arc};
+    #[cfg(feature = "std")]
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct Shared(Arc<String>);
+
+    // Nothing but safe code here, so that Miri can check that cloning and dropping the clone
+    // touches nothing it shouldn’t.
+    macro_rules! test_clone {
+        ($name:ident, $t:ty) => {
+            #[test]
+            fn $name() {
+                let shared = Arc::new(String::from("shared"));
+                let original: Box<$t> = Box::new(Shared(shared.clone()));
+                assert_eq!(Arc::strong_count(&shared), 2);
+
+                let mut clone = original.clone();
+                assert_eq!(Arc::strong_count(&shared), 3);
+                assert_eq!(Downcast::type_id(&*clone), TypeId::of::<Shared>());
+                {
+                    let clone: &mut dyn Any = &mut *clone;
+                    let clone = clone.downcast_mut::<Shared>().unwrap();
+                    assert!(Arc::ptr_eq(&clone.0, &shared));
+                    clone.0 = Arc::new(String::from("replaced"));
+                }
+                assert_eq!(Arc::strong_count(&shared), 2);
+
+                let original: Box<dyn Any> = original;
+                assert_eq!(original.downcast_ref::<Shared>(), Some(&Shared(shared.clone())));
+                drop(original);
+                drop(clone);
+                assert_eq!(Arc::strong_count(&shared), 1);
+            }
+        }
+    }
+
+    test_clone!(clone_any, dyn CloneAny);
+    test_clone!(clone_any_send, dyn CloneAny + Send);
+    test_clone!(clone_any_sync, dyn CloneAny + Sync);
+    test_clone!(clone_any_send_sync, dyn CloneAny + Send + Sync);
+    test_clone!(clone_debug_any, dyn CloneDebugAny);
+    test_clone!(clone_debug_any_send, dyn CloneDebugAny + Send);
+    test_clone!(clone_debug_any_sync, dyn CloneDebugAny + Sync);
+    test_clone!(clone_debug_any_send_sync, dyn CloneDebugAny + Send + Sync);
+    test_clone!(clone_eq_any, dyn CloneEqAny);
+    test_clone!(clone_eq_any_send, dyn CloneEqAny + Send);
+    test_clone!(clone_eq_any_sync, dyn CloneEqAny + Sync);
+    test_clone!(clone_eq_any_send_sync, dyn CloneEqAny + Send + Sync);
+    test_clone!(clone_hash_any, dyn CloneHashAny);
+    test_clone!(clone_hash_any_send, dyn CloneHashAny + Send);
+    test_clone!(clone_hash_any_sync, dyn CloneHashAny + Sync);
+    test_clone!(clone_hash_any_send_sync, dyn CloneHashAny + Send + Sync);
+
+    #[test]
+    fn type_names() {
+        let value = Shared(Arc::default());
+        let name = Some(core::any::type_name::<Shared>());
+        assert_eq!(Downcast::type_name(&value as &dyn Any), None);
+        assert_eq!(Downcast::type_name(&value as &(dyn Any + Send + Sync)), None);
+        assert_eq!(Downcast::type_name(&value as &dyn CloneAny), name);
+        assert_eq!(Downcast::type_name(&value as &(dyn DebugAny + Send)), name);
+        assert_eq!(Downcast::type_name(&value as &(dyn HashAny + Sync)), name);
+        assert_eq!(Downcast::type_name(&value as &(dyn CloneHashAny + Send + Sync)), name);
+    }
+
+    #[test]
+    fn clone_keeps_auto_traits() {
+        fn assert_send<T: Send>(_: &T) { }
+        fn assert_sync<T: Sync>(_: &T) { }
+        let original: Box<dyn CloneAny + Send + Sync> = Box::new(Shared(Arc::default()));
+        let clone = original.clone();
+        assert_send(&clone);
+        assert_sync(&clone);
+        let original: Box<dyn CloneAny + Send> = original;
+        assert_send(&original.clone());
+        let original: Box<dyn CloneAny + Sync> = Box::new(Shared(Arc::default()));
+        assert_sync(&original.clone());
+    }
+}
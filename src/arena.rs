@@ -0,0 +1,488 @@
+//! A `Map` that keeps its values in an arena, to be freed all at once.
+
+use core::alloc::Layout;
+use core::any::{Any, TypeId};
+use core::cmp;
+use core::fmt;
+use core::hash::BuildHasherDefault;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::{self, NonNull};
+
+#[cfg(not(feature = "std"))]
+use alloc::{alloc::{alloc, dealloc, handle_alloc_error}, vec::Vec};
+#[cfg(feature = "std")]
+use std::alloc::{alloc, dealloc, handle_alloc_error};
+#[cfg(feature = "std")]
+use std::collections::hash_map::{self, HashMap};
+#[cfg(not(feature = "std"))]
+use hashbrown::hash_map::{self, HashMap};
+
+use crate::{Downcast, IntoBox, TypeIdHasher};
+
+/// The alignment of each chunk; values needing more are aligned within the chunk.
+const CHUNK_ALIGN: usize = 16;
+
+/// The size of the first chunk; each after that is at least twice the size of the last.
+const FIRST_CHUNK_SIZE: usize = 256;
+
+/// A region that values are bump-allocated in, and freed from all at once.
+///
+/// The chunks are only ever handled through raw pointers, so that the values in them can be too.
+struct Arena {
+    /// The chunks allocated so far, with their layouts, the current one last.
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    /// How many bytes of the current chunk are used.
+    used: usize,
+}
+
+impl Arena {
+    #[inline]
+    fn new() -> Arena {
+        Arena {
+            chunks: Vec::new(),
+            used: 0,
+        }
+    }
+
+    /// Allocates space for a value with the given layout, which lasts until `reset` or drop.
+    fn alloc(&mut self, layout: Layout) -> NonNull<u8> {
+        if let Some(&(chunk, chunk_layout)) = self.chunks.last() {
+            let end = unsafe { chunk.as_ptr().add(self.used) };
+            let start = self.used + end.align_offset(layout.align());
+            if start.checked_add(layout.size()).is_some_and(|end| end <= chunk_layout.size()) {
+                self.used = start + layout.size();
+                return unsafe { NonNull::new_unchecked(chunk.as_ptr().add(start)) };
+            }
+        }
+        let last_size = self.chunks.last().map_or(0, |(_, chunk_layout)| chunk_layout.size());
+        let size = cmp::max(
+            cmp::max(last_size * 2, FIRST_CHUNK_SIZE),
+            layout.size() + layout.align(),
+        );
+        let chunk_layout = match Layout::from_size_align(size, CHUNK_ALIGN) {
+            Ok(chunk_layout) => chunk_layout,
+            Err(_) => handle_alloc_error(layout),
+        };
+        let chunk = match NonNull::new(unsafe { alloc(chunk_layout) }) {
+            Some(chunk) => chunk,
+            None => handle_alloc_error(chunk_layout),
+        };
+        self.chunks.push((chunk, chunk_layout));
+        let start = chunk.as_ptr().align_offset(layout.align());
+        self.used = start + layout.size();
+        unsafe { NonNull::new_unchecked(chunk.as_ptr().add(start)) }
+    }
+
+    /// Frees everything, keeping only the current (and biggest) chunk for reuse.
+    fn reset(&mut self) {
+        let last = self.chunks.len().saturating_sub(1);
+        for (chunk, chunk_layout) in self.chunks.drain(..last) {
+            unsafe { dealloc(chunk.as_ptr(), chunk_layout) }
+        }
+        self.used = 0;
+    }
+
+    /// The number of bytes allocated for chunks.
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.chunks.iter().map(|(_, chunk_layout)| chunk_layout.size()).sum()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for &(chunk, chunk_layout) in &self.chunks {
+            unsafe { dealloc(chunk.as_ptr(), chunk_layout) }
+        }
+    }
+}
+
+/// A value in the arena, and whether it needs dropping.
+struct Slot<A: ?Sized> {
+    value: NonNull<A>,
+    needs_drop: bool,
+}
+
+impl<A: ?Sized> Slot<A> {
+    /// Drops the value, if it has drop glue. The slot must not be used again.
+    #[inline]
+    unsafe fn drop_value(self) {
+        if self.needs_drop {
+            ptr::drop_in_place(self.value.as_ptr())
+        }
+    }
+}
+
+/// Raw access to the underlying `HashMap`, from `TypeId`s to the values in the arena.
+type RawMap<A> = HashMap<TypeId, Slot<A>, BuildHasherDefault<TypeIdHasher>>;
+
+#[cfg(feature = "std")]
+type RawOccupiedEntry<'a, A> = hash_map::OccupiedEntry<'a, TypeId, Slot<A>>;
+#[cfg(not(feature = "std"))]
+type RawOccupiedEntry<'a, A> =
+    hash_map::OccupiedEntry<'a, TypeId, Slot<A>, BuildHasherDefault<TypeIdHasher>>;
+
+#[cfg(feature = "std")]
+type RawVacantEntry<'a, A> = hash_map::VacantEntry<'a, TypeId, Slot<A>>;
+#[cfg(not(feature = "std"))]
+type RawVacantEntry<'a, A> =
+    hash_map::VacantEntry<'a, TypeId, Slot<A>, BuildHasherDefault<TypeIdHasher>>;
+
+/// A collection containing zero or one values for any given type and allowing convenient,
+/// type-safe access to those values, keeping the values in an arena that the map owns.
+///
+/// Where an ordinary [`Map`](crate::Map) allocates a box for each value and frees it when the
+/// value is removed, this bump-allocates each value in chunks of memory that belong to the map.
+/// [`clear`](Map::clear) and dropping the map free the lot at once, only visiting the values that
+/// have drop glue to drop them; `clear` also keeps the biggest chunk, so a map that’s cleared and
+/// refilled (say, one per request, reused) soon stops allocating altogether. Removing a value
+/// doesn’t free its space until then, and nor does replacing one with a value of another type.
+/// (Replacing a value with one of the same type reuses its space.)
+///
+/// The value type `A` is as for [`Map`](crate::Map): `Any`, [`CloneAny`](crate::CloneAny),
+/// [`DebugAny`](crate::DebugAny), *&c.*, with `+ Send` and/or `+ Sync`. This has the typed API of
+/// `Map` (`get`, `get_mut`, `insert`, `remove`, `contains` and `entry`), but it isn’t `Clone`.
+///
+/// ## Example
+///
+/// ```rust
+/// let mut data = anymap::arena::AnyMap::new();
+/// for request in 0..3 {
+///     data.insert(request);
+///     data.insert(format!("request {}", request));
+///     assert_eq!(data.get::<String>().map(|s| &**s), Some(&*format!("request {}", request)));
+///     data.clear();
+/// }
+/// ```
+pub struct Map<A: ?Sized + Downcast = dyn Any> {
+    raw: RawMap<A>,
+    arena: Arena,
+}
+
+/// The most common type of [`Map`]: just using `Any`; <code>[Map]&lt;dyn [Any]&gt;</code>.
+pub type AnyMap = Map<dyn Any>;
+
+// The map owns its values, as if they were boxed.
+unsafe impl<A: ?Sized + Downcast + Send> Send for Map<A> {}
+unsafe impl<A: ?Sized + Downcast + Sync> Sync for Map<A> {}
+
+impl<A: ?Sized + Downcast> Default for Map<A> {
+    #[inline]
+    fn default() -> Map<A> {
+        Map::new()
+    }
+}
+
+impl<A: ?Sized + Downcast + fmt::Debug> fmt::Debug for Map<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.raw.iter().map(|(type_id, slot)| {
+                let value = unsafe { slot.value.as_ref() };
+                (crate::TypeName::new(*type_id, Downcast::type_name(value)), value)
+            }))
+            .finish()
+    }
+}
+
+impl<A: ?Sized + Downcast> Drop for Map<A> {
+    fn drop(&mut self) {
+        for (_, slot) in self.raw.drain() {
+            unsafe { slot.drop_value() }
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast> Map<A> {
+    /// Create an empty collection.
+    #[inline]
+    pub fn new() -> Map<A> {
+        Map {
+            raw: RawMap::with_hasher(Default::default()),
+            arena: Arena::new(),
+        }
+    }
+
+    /// Returns the number of items in the collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns true if there are no items in the collection.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Returns the number of bytes the arena has allocated for values.
+    #[inline]
+    pub fn arena_capacity(&self) -> usize {
+        self.arena.capacity()
+    }
+
+    /// Removes all items from the collection, dropping those that need it, and frees the arena,
+    /// keeping its biggest chunk of memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        for (_, slot) in self.raw.drain() {
+            unsafe { slot.drop_value() }
+        }
+        self.arena.reset()
+    }
+
+    /// Returns a reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.raw.get(&TypeId::of::<T>())
+            .map(|slot| unsafe { slot.value.cast::<T>().as_ref() })
+    }
+
+    /// Returns a mutable reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.raw.get_mut(&TypeId::of::<T>())
+            .map(|slot| unsafe { slot.value.cast::<T>().as_mut() })
+    }
+
+    /// Sets the value stored in the collection for the type `T`.
+    /// If the collection already had a value of type `T`, that value is returned.
+    /// Otherwise, `None` is returned.
+    #[inline]
+    pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        match self.entry::<T>() {
+            Entry::Occupied(mut inner) => Some(inner.insert(value)),
+            Entry::Vacant(inner) => {
+                let _ = inner.insert(value);
+                None
+            },
+        }
+    }
+
+    /// Removes the `T` value from the collection,
+    /// returning it if there was one or `None` if there was not.
+    ///
+    /// Its space in the arena isn’t freed until the collection is cleared or dropped.
+    #[inline]
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        self.raw.remove(&TypeId::of::<T>())
+            .map(|slot| unsafe { ptr::read(slot.value.cast::<T>().as_ptr()) })
+    }
+
+    /// Returns true if the collection contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.raw.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Gets the entry for the given type in the collection for in-place manipulation
+    #[inline]
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T> {
+        match self.raw.entry(TypeId::of::<T>()) {
+            hash_map::Entry::Occupied(e) => Entry::Occupied(OccupiedEntry {
+                inner: e,
+                type_: PhantomData,
+            }),
+            hash_map::Entry::Vacant(e) => Entry::Vacant(VacantEntry {
+                inner: e,
+                arena: &mut self.arena,
+                type_: PhantomData,
+            }),
+        }
+    }
+}
+
+/// A view into a single occupied location in an arena `Map`.
+pub struct OccupiedEntry<'a, A: ?Sized + Downcast, V: 'a> {
+    inner: RawOccupiedEntry<'a, A>,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single empty location in an arena `Map`.
+pub struct VacantEntry<'a, A: ?Sized + Downcast, V: 'a> {
+    inner: RawVacantEntry<'a, A>,
+    arena: &'a mut Arena,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single location in an arena `Map`, which may be vacant or occupied.
+pub enum Entry<'a, A: ?Sized + Downcast, V: 'a> {
+    /// An occupied Entry
+    Occupied(OccupiedEntry<'a, A, V>),
+    /// A vacant Entry
+    Vacant(VacantEntry<'a, A, V>),
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> Entry<'a, A, V> {
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if
+    /// empty, and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_default(self) -> &'a mut V where V: Default {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(Default::default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts
+    /// into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut inner) => {
+                f(inner.get_mut());
+                Entry::Occupied(inner)
+            },
+            Entry::Vacant(inner) => Entry::Vacant(inner),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> OccupiedEntry<'a, A, V> {
+    /// Gets a reference to the value in the entry
+    #[inline]
+    pub fn get(&self) -> &V {
+        unsafe { self.inner.get().value.cast::<V>().as_ref() }
+    }
+
+    /// Gets a mutable reference to the value in the entry
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.inner.get_mut().value.cast::<V>().as_mut() }
+    }
+
+    /// Converts the OccupiedEntry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the collection itself
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { self.inner.into_mut().value.cast::<V>().as_mut() }
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry, and returns it
+    #[inline]
+    pub fn remove(self) -> V {
+        unsafe { ptr::read(self.inner.remove().value.cast::<V>().as_ptr()) }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> VacantEntry<'a, A, V> {
+    /// Sets the value of the entry with the VacantEntry's key,
+    /// and returns a mutable reference to it
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let ptr = self.arena.alloc(Layout::new::<V>()).cast::<V>();
+        unsafe {
+            ptr.as_ptr().write(value);
+            let slot = self.inner.insert(Slot {
+                value: NonNull::new_unchecked(V::as_dyn_ptr(ptr.as_ptr())),
+                needs_drop: mem::needs_drop::<V>(),
+            });
+            slot.value.cast::<V>().as_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use crate::DebugAny;
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, string::{String, ToString}};
+
+    #[derive(Debug, PartialEq)] struct A(u8);
+    #[derive(Debug, PartialEq)] struct B(u64);
+    #[derive(Debug, PartialEq)] #[repr(align(64))] struct Aligned(u8);
+    #[derive(Debug, PartialEq)] struct Big([u8; 1000]);
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Dropper(#[allow(dead_code)] String);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) {
+            let _ = DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_arena() {
+        let mut map: Map<dyn DebugAny> = Map::new();
+        assert_eq!(map.insert(A(1)), None);
+        assert_eq!(map.insert(B(2)), None);
+        assert_eq!(map.insert(Aligned(3)), None);
+        assert_eq!(map.insert(Big([4; 1000])), None);
+        assert_eq!(map.insert(()), None);
+        assert_eq!(map.insert(A(10)), Some(A(1)));
+        assert_eq!(map.get::<A>(), Some(&A(10)));
+        assert_eq!(map.get::<Aligned>(), Some(&Aligned(3)));
+        assert_eq!(map.get::<Aligned>().unwrap() as *const Aligned as usize % 64, 0);
+        map.get_mut::<B>().unwrap().0 += 1;
+        assert_eq!(map.get::<B>(), Some(&B(3)));
+        assert_eq!(map.remove::<Big>().map(|big| big.0[999]), Some(4));
+        assert!(!map.contains::<Big>());
+        assert_eq!(map.len(), 4);
+
+        *map.entry::<A>().or_insert(A(0)) = A(11);
+        assert_eq!(*map.entry::<u8>().and_modify(|v| *v += 1).or_default(), 0);
+        match map.entry::<A>() {
+            Entry::Occupied(view) => assert_eq!(view.remove(), A(11)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(format!("{:?}", map).contains("B(3)"));
+
+        let capacity = map.arena_capacity();
+        assert!(capacity >= 1000);
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.arena_capacity() <= capacity);
+        assert_eq!(map.insert(Big([5; 1000])), None);
+        assert_eq!(map.get::<Big>().map(|big| big.0[0]), Some(5));
+    }
+
+    #[test]
+    fn test_arena_drops() {
+        let mut map = AnyMap::new();
+        let _ = map.insert(Dropper("one".to_string()));
+        let _ = map.insert(A(1));
+        let _ = map.insert(Dropper("two".to_string()));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        let _ = map.remove::<Dropper>();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        let _ = map.insert(Dropper("three".to_string()));
+        map.clear();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        let _ = map.insert(Dropper("four".to_string()));
+        drop(map);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+}
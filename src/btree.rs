@@ -0,0 +1,77 @@
+//! A `Map` backed by `BTreeMap`, for deterministic iteration order.
+
+use core::any::TypeId;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::BTreeMap};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+/// Raw access to the underlying `BTreeMap`, which is the storage behind a [`Map`].
+pub type RawMap<A> = BTreeMap<TypeId, Box<A>>;
+
+map_aliases! {
+    /// A collection containing zero or one values for any given type and allowing convenient,
+    /// type-safe access to those values, kept in order of `TypeId` in a `BTreeMap`.
+    ///
+    /// This is <code>anymap::[Map](crate::Map)&lt;A, [RawMap]&lt;A&gt;&gt;</code>, so it has the
+    /// same API as the hash-map-backed `Map`, apart from having no capacity to manage, and works
+    /// with the same value types (`Any`, [`CloneAny`](crate::CloneAny),
+    /// [`DebugAny`](crate::DebugAny), *&c.*, with `+ Send` and/or `+ Sync`). The difference is
+    /// that iterating over it, whether through [`iter`](crate::Map::iter), `Debug` output or
+    /// [`as_raw`](crate::Map::as_raw), always visits the items in the same order, rather than an
+    /// order that varies from one run to the next. (`TypeId`s can still change between builds of
+    /// your program, and with them the order.)
+    ///
+    /// It only needs `alloc`, so it’s available without the std or hashbrown Cargo features.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut data = anymap::btree::AnyMap::new();
+    /// data.insert(42i32);
+    /// data.insert("forty-two");
+    /// assert_eq!(data.get(), Some(&42i32));
+    /// let mut other = anymap::btree::AnyMap::new();
+    /// other.insert("forty-two");
+    /// other.insert(42i32);
+    /// assert!(data.iter().map(|(type_id, _)| type_id).eq(other.iter().map(|(type_id, _)| type_id)));
+    /// ```
+    RawMap
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CloneDebugAny, Downcast};
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec::Vec};
+
+    #[derive(Clone, Debug, PartialEq)] struct A(i32);
+    #[derive(Clone, Debug, PartialEq)] struct B(i32);
+    #[derive(Clone, Debug, PartialEq)] struct C(i32);
+    #[derive(Clone, Debug, PartialEq)] struct J(i32);
+
+    #[test]
+    fn test_order() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        let _ = map.insert(C(3));
+        let _ = map.insert(A(1));
+        let _ = map.insert(J(7));
+        let _ = map.insert(B(2));
+        let mut map2: Map<dyn CloneDebugAny> = Map::new();
+        let _ = map2.insert(B(2));
+        let _ = map2.insert(J(7));
+        let _ = map2.insert(A(1));
+        let _ = map2.insert(C(3));
+
+        let type_ids = map.iter().map(|(type_id, _)| type_id).collect::<Vec<_>>();
+        let mut sorted = type_ids.clone();
+        sorted.sort();
+        assert_eq!(type_ids, sorted);
+        assert_eq!(map2.iter().map(|(type_id, _)| type_id).collect::<Vec<_>>(), type_ids);
+        assert_eq!(map.type_names().collect::<Vec<_>>(), map2.type_names().collect::<Vec<_>>());
+        assert_eq!(format!("{:?}", map), format!("{:?}", map2));
+        assert!(map.clone().into_iter().map(|value| Downcast::type_id(&*value)).eq(sorted));
+    }
+}
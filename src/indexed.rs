@@ -0,0 +1,508 @@
+//! A `Map` that remembers the order in which items were inserted.
+
+use core::any::TypeId;
+use core::fmt;
+use core::hash::BuildHasherDefault;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::{self, Vec}};
+#[cfg(feature = "std")]
+use std::{collections::hash_map::{self, HashMap}, vec};
+#[cfg(not(feature = "std"))]
+use hashbrown::hash_map::{self, HashMap};
+
+use crate::storage::{
+    RawCapacity, RawEntry, RawOccupiedEntry, RawStorage, RawVacantEntry, SliceIter, SliceIterMut,
+};
+use crate::recycle;
+use crate::{Downcast, IntoBox, TypeIdHasher};
+
+/// Where each type is in a `RawMap`.
+type IndexMap = HashMap<TypeId, usize, BuildHasherDefault<TypeIdHasher>>;
+
+/// The storage behind an indexed [`Map`]: the items in a `Vec`, in the order in which they were
+/// first inserted, with a `TypeId`-keyed `HashMap` of their positions.
+pub struct RawMap<A: ?Sized> {
+    items: Vec<(TypeId, Box<A>)>,
+    indices: IndexMap,
+}
+
+map_aliases! {
+    /// A collection containing zero or one values for any given type and allowing convenient,
+    /// type-safe access to those values, remembering the order in which they were inserted.
+    ///
+    /// This is <code>anymap::[Map](crate::Map)&lt;A, [RawMap]&lt;A&gt;&gt;</code>, which keeps
+    /// the items in a `Vec`, in insertion order, with a `TypeId`-keyed `HashMap` of their
+    /// indexes. It has the same API as the other `Map`s, and works with the same value types
+    /// (`Any`, [`CloneAny`](crate::CloneAny), [`DebugAny`](crate::DebugAny), *&c.*, with
+    /// `+ Send` and/or `+ Sync`); but iteration, `Debug` output and
+    /// [`as_raw`](crate::Map::as_raw) are in the order in which the items were first inserted.
+    /// (Replacing a value keeps its place.)
+    ///
+    /// On top of that, you can look items up by their position with
+    /// [`get_index`](crate::Map::get_index), and choose how to remove items:
+    /// [`remove`](crate::Map::remove) and [`shift_remove`](crate::Map::shift_remove) shift the
+    /// items after it down to preserve the order, taking time proportional to their number,
+    /// while [`swap_remove`](crate::Map::swap_remove) is quicker, moving the last item into the
+    /// removed item’s place.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut data = anymap::indexed::AnyMap::new();
+    /// data.insert(1u8);
+    /// data.insert("two");
+    /// data.insert(3.0f64);
+    /// assert_eq!(data.get_index_of::<&str>(), Some(1));
+    /// assert_eq!(data.shift_remove::<u8>(), Some(1));
+    /// assert_eq!(data.get_index(0).and_then(|(_, value)| value.downcast_ref()), Some(&"two"));
+    /// data.insert(4u8);
+    /// assert_eq!(data.swap_remove::<&str>(), Some("two"));
+    /// assert_eq!(data.get_index(0).and_then(|(_, value)| value.downcast_ref()), Some(&4u8));
+    /// ```
+    RawMap
+}
+
+impl<A: ?Sized> RawMap<A> {
+    /// Creates empty storage.
+    #[inline]
+    pub fn new() -> RawMap<A> {
+        RawMap {
+            items: Vec::new(),
+            indices: IndexMap::default(),
+        }
+    }
+
+    /// Returns the items, in order.
+    #[inline]
+    pub fn as_slice(&self) -> &[(TypeId, Box<A>)] {
+        &self.items
+    }
+
+    /// Converts this into a `Vec` of the items, in order.
+    #[inline]
+    pub fn into_vec(self) -> Vec<(TypeId, Box<A>)> {
+        self.items
+    }
+
+    /// Returns the position of the item for `type_id`, if there is one.
+    #[inline]
+    pub fn get_index_of(&self, type_id: TypeId) -> Option<usize> {
+        self.indices.get(&type_id).cloned()
+    }
+
+    /// Removes the item for `type_id` by shifting all the items after it down, returning its
+    /// value if there was one.
+    #[inline]
+    pub fn shift_remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        let index = self.get_index_of(type_id)?;
+        Some(self.shift_remove_index(index))
+    }
+
+    /// Removes the item for `type_id` by moving the last item into its place, returning its
+    /// value if there was one.
+    #[inline]
+    pub fn swap_remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        let index = self.get_index_of(type_id)?;
+        Some(self.swap_remove_index(index))
+    }
+
+    /// Removes the item at `index` by shifting all the items after it down.
+    fn shift_remove_index(&mut self, index: usize) -> Box<A> {
+        let (type_id, value) = self.items.remove(index);
+        let _ = self.indices.remove(&type_id);
+        for (type_id, _) in &self.items[index..] {
+            if let Some(i) = self.indices.get_mut(type_id) {
+                *i -= 1;
+            }
+        }
+        value
+    }
+
+    /// Removes the item at `index` by moving the last item into its place.
+    fn swap_remove_index(&mut self, index: usize) -> Box<A> {
+        let (type_id, value) = self.items.swap_remove(index);
+        let _ = self.indices.remove(&type_id);
+        if let Some((moved, _)) = self.items.get(index) {
+            let _ = self.indices.insert(*moved, index);
+        }
+        value
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for RawMap<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> RawMap<A> {
+        RawMap {
+            items: self.items.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl<A: ?Sized> Default for RawMap<A> {
+    #[inline]
+    fn default() -> RawMap<A> {
+        RawMap::new()
+    }
+}
+
+impl<A: ?Sized + fmt::Debug> fmt::Debug for RawMap<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.items.iter().map(|(type_id, value)| (type_id, value))).finish()
+    }
+}
+
+impl<A: ?Sized> IntoIterator for RawMap<A> {
+    type Item = (TypeId, Box<A>);
+    type IntoIter = vec::IntoIter<(TypeId, Box<A>)>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+unsafe impl<A: ?Sized> RawStorage<A> for RawMap<A> {
+    type Iter<'a> = SliceIter<'a, A> where A: 'a;
+    type IterMut<'a> = SliceIterMut<'a, A> where A: 'a;
+    type Drain<'a> = vec::Drain<'a, (TypeId, Box<A>)> where A: 'a;
+    type OccupiedEntry<'a> = IndexedOccupiedEntry<'a, A> where A: 'a;
+    type VacantEntry<'a> = IndexedVacantEntry<'a, A> where A: 'a;
+    type Rebind<B: ?Sized> = RawMap<B>;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.items.clear();
+        self.indices.clear()
+    }
+
+    #[inline]
+    fn get(&self, type_id: TypeId) -> Option<&A> {
+        self.indices.get(&type_id).map(|&index| &*self.items[index].1)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+        match self.indices.get(&type_id) {
+            Some(&index) => Some(&mut *self.items[index].1),
+            None => None,
+        }
+    }
+
+    #[inline]
+    fn contains_key(&self, type_id: TypeId) -> bool {
+        self.indices.contains_key(&type_id)
+    }
+
+    /// Inserts a value for a `TypeId`, keeping its place if it’s already there, or adding it to
+    /// the end if not, and returning the old value if any.
+    #[inline]
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+        let items = &mut self.items;
+        match self.indices.entry(type_id) {
+            hash_map::Entry::Occupied(e) => {
+                Some(core::mem::replace(&mut items[*e.get()].1, value))
+            },
+            hash_map::Entry::Vacant(e) => {
+                let _ = e.insert(items.len());
+                items.push((type_id, value));
+                None
+            },
+        }
+    }
+
+    /// Removes the value for a `TypeId` by shifting all the following items down.
+    #[inline]
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        self.shift_remove(type_id)
+    }
+
+    #[inline]
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+    {
+        match self.get_index_of(type_id) {
+            Some(index) => RawEntry::Occupied(IndexedOccupiedEntry {
+                raw: self,
+                index,
+            }),
+            None => RawEntry::Vacant(IndexedVacantEntry {
+                raw: self,
+                type_id,
+            }),
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        SliceIter::new(&self.items)
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        SliceIterMut::new(&mut self.items)
+    }
+
+    #[inline]
+    fn drain(&mut self) -> Self::Drain<'_> {
+        self.indices.clear();
+        self.items.drain(..)
+    }
+
+    /// Retains only the items specified by the predicate, preserving the order of the rest.
+    #[inline]
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        self.items.retain_mut(|(type_id, value)| f(*type_id, &mut **value));
+        self.indices.clear();
+        self.indices.extend(self.items.iter().enumerate().map(|(index, (type_id, _))| {
+            (*type_id, index)
+        }));
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.indices.reserve(additional)
+    }
+}
+
+impl<A: ?Sized> RawCapacity<A> for RawMap<A> {
+    #[inline]
+    fn with_capacity(capacity: usize) -> RawMap<A> {
+        RawMap {
+            items: Vec::with_capacity(capacity),
+            indices: IndexMap::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.items.capacity().min(self.indices.capacity())
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit();
+        self.indices.shrink_to_fit()
+    }
+}
+
+/// A view into an occupied place in an indexed [`RawMap`].
+pub struct IndexedOccupiedEntry<'a, A: ?Sized> {
+    raw: &'a mut RawMap<A>,
+    index: usize,
+}
+
+/// A view into a vacant place in an indexed [`RawMap`], which is at the end.
+pub struct IndexedVacantEntry<'a, A: ?Sized> {
+    raw: &'a mut RawMap<A>,
+    type_id: TypeId,
+}
+
+impl<'a, A: ?Sized> RawOccupiedEntry<'a, A> for IndexedOccupiedEntry<'a, A> {
+    #[inline]
+    fn get(&self) -> &A {
+        &self.raw.items[self.index].1
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut A {
+        &mut self.raw.items[self.index].1
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut A {
+        &mut self.raw.items[self.index].1
+    }
+
+    #[inline]
+    fn insert(&mut self, value: Box<A>) -> Box<A> {
+        core::mem::replace(&mut self.raw.items[self.index].1, value)
+    }
+
+    #[inline]
+    fn remove(self) -> Box<A> {
+        self.raw.shift_remove_index(self.index)
+    }
+}
+
+impl<'a, A: ?Sized> RawVacantEntry<'a, A> for IndexedVacantEntry<'a, A> {
+    #[inline]
+    fn insert(self, value: Box<A>) -> &'a mut A {
+        let index = self.raw.items.len();
+        let _ = self.raw.indices.insert(self.type_id, index);
+        self.raw.items.push((self.type_id, value));
+        &mut self.raw.items[index].1
+    }
+}
+
+/// The methods peculiar to an indexed `Map`.
+impl<A: ?Sized + Downcast> crate::Map<A, RawMap<A>> {
+    /// Removes the `T` value from the collection by shifting all the items after it down,
+    /// returning it if there was one or `None` if there was not.
+    ///
+    /// This preserves the order of the other items, but takes time proportional to the number
+    /// of items after it. It’s what [`remove`](crate::Map::remove) does.
+    #[inline]
+    pub fn shift_remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        let recycler = &mut self.recycler;
+        self.raw.shift_remove(TypeId::of::<T>())
+            .map(|any| unsafe { recycle::unbox(recycler, any) })
+    }
+
+    /// Removes the `T` value from the collection by moving the last item into its place,
+    /// returning it if there was one or `None` if there was not.
+    ///
+    /// This takes constant time, but changes the order of the items.
+    #[inline]
+    pub fn swap_remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        let recycler = &mut self.recycler;
+        self.raw.swap_remove(TypeId::of::<T>())
+            .map(|any| unsafe { recycle::unbox(recycler, any) })
+    }
+
+    /// Returns the position of the `T` value in the collection, if there is one.
+    #[inline]
+    pub fn get_index_of<T: IntoBox<A>>(&self) -> Option<usize> {
+        self.raw.get_index_of(TypeId::of::<T>())
+    }
+
+    /// Returns the item at the given position in the collection, as its `TypeId` and a
+    /// reference to its value, or `None` if `index` is out of bounds.
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(TypeId, &A)> {
+        self.raw.items.get(index).map(|(type_id, value)| (*type_id, &**value))
+    }
+
+    /// Returns the item at the given position in the collection, as its `TypeId` and a
+    /// mutable reference to its value, or `None` if `index` is out of bounds.
+    #[inline]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(TypeId, &mut A)> {
+        self.raw.items.get_mut(index).map(|(type_id, value)| (*type_id, &mut **value))
+    }
+}
+
+/// The methods peculiar to an entry in an indexed `Map`.
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> crate::OccupiedEntry<'a, A, V, RawMap<A>> {
+    /// Returns the position of the entry in the collection
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.inner.index
+    }
+
+    /// Takes the value out of the entry, and returns it, shifting the items after it down
+    #[inline]
+    pub fn shift_remove(self) -> V {
+        self.remove()
+    }
+
+    /// Takes the value out of the entry, and returns it, moving the last item into its place
+    #[inline]
+    pub fn swap_remove(self) -> V {
+        let value = self.inner.raw.swap_remove_index(self.inner.index);
+        unsafe { recycle::unbox(self.recycler, value) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::any::type_name;
+    use crate::{CloneDebugAny, Downcast};
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    #[derive(Clone, Debug, PartialEq)] struct A(i32);
+    #[derive(Clone, Debug, PartialEq)] struct B(i32);
+    #[derive(Clone, Debug, PartialEq)] struct C(i32);
+    #[derive(Clone, Debug, PartialEq)] struct D(i32);
+    #[derive(Clone, Debug, PartialEq)] struct J(i32);
+
+    #[test]
+    fn test_order() {
+        #[cfg(not(feature = "std"))]
+        use alloc::format;
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        let _ = map.insert(C(3));
+        let _ = map.insert(A(1));
+        let _ = map.insert(J(7));
+        let _ = map.entry::<B>().or_insert(B(2));
+        let _ = map.insert(A(10));
+        let order = [TypeId::of::<C>(), TypeId::of::<A>(), TypeId::of::<J>(), TypeId::of::<B>()];
+        assert!(map.iter().map(|(type_id, _)| type_id).eq(order.iter().cloned()));
+        assert!(map.iter().rev().map(|(type_id, _)| type_id).eq(order.iter().rev().cloned()));
+        assert_eq!(
+            map.type_names().collect::<Vec<_>>(),
+            [type_name::<C>(), type_name::<A>(), type_name::<J>(), type_name::<B>()],
+        );
+        assert_eq!(
+            format!("{:?}", map),
+            format!("{{{}: C(3), {}: A(10), {}: J(7), {}: B(2)}}",
+                type_name::<C>(), type_name::<A>(), type_name::<J>(), type_name::<B>()),
+        );
+        assert!(map.clone().into_iter().map(|value| Downcast::type_id(&*value)).eq(order));
+        assert!(map.as_raw().as_slice().iter().map(|&(type_id, _)| type_id).eq(order));
+
+        assert_eq!(map.get_index_of::<J>(), Some(2));
+        assert_eq!(map.get_index_of::<D>(), None);
+        assert_eq!(map.get_index(1).map(|(type_id, _)| type_id), Some(TypeId::of::<A>()));
+        assert!(map.get_index(4).is_none());
+        let (_, value) = map.get_index_mut(3).unwrap();
+        *unsafe { value.downcast_mut_unchecked::<B>() } = B(20);
+        assert_eq!(map.get(), Some(&B(20)));
+
+        // Shifting keeps the order of the rest; swapping moves the last into place.
+        let mut shifted = map.clone();
+        assert_eq!(shifted.shift_remove::<C>(), Some(C(3)));
+        assert!(shifted.iter().map(|(type_id, _)| type_id).eq(order[1..].iter().cloned()));
+        assert_eq!(shifted.get_index_of::<B>(), Some(2));
+        assert_eq!(shifted.get(), Some(&J(7)));
+        assert_eq!(map.swap_remove::<C>(), Some(C(3)));
+        assert_eq!(map.swap_remove::<C>(), None);
+        assert!(map.iter().map(|(type_id, _)| type_id).eq([order[3], order[1], order[2]]));
+        assert_eq!(map.get_index_of::<B>(), Some(0));
+        assert_eq!(map.get(), Some(&B(20)));
+        assert_eq!(map.type_name(TypeId::of::<C>()), None);
+
+        match map.entry::<A>() {
+            Entry::Vacant(_) => unreachable!(),
+            Entry::Occupied(view) => {
+                assert_eq!(view.index(), 1);
+                assert_eq!(view.swap_remove(), A(10));
+            },
+        }
+        assert_eq!(map.get_index_of::<J>(), Some(1));
+        map.retain(|type_id, _| type_id != TypeId::of::<B>());
+        assert_eq!(map.get_index_of::<J>(), Some(0));
+        assert_eq!(map.get(), Some(&J(7)));
+    }
+
+    #[test]
+    fn test_recycling() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        map.set_recycling(true);
+        let _ = map.insert_all((A(1), B(2), C(3)));
+        let a = map.get::<A>().unwrap() as *const A;
+        let b = map.get::<B>().unwrap() as *const B;
+        let c = map.get::<C>().unwrap() as *const C;
+        assert_eq!(map.shift_remove::<A>(), Some(A(1)));
+        assert_eq!(map.swap_remove::<B>(), Some(B(2)));
+        match map.entry::<C>() {
+            Entry::Occupied(view) => assert_eq!(view.swap_remove(), C(3)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(map.recycled_len(), 3);
+        let _ = map.insert_all((C(4), B(5), A(6)));
+        assert_eq!(map.recycled_len(), 0);
+        assert_eq!(map.get::<A>().unwrap() as *const A, a);
+        assert_eq!(map.get::<B>().unwrap() as *const B, b);
+        assert_eq!(map.get::<C>().unwrap() as *const C, c);
+    }
+}
@@ -0,0 +1,1025 @@
+//! A `Map` that stores small values inline rather than boxing them.
+
+use core::any::{Any, TypeId};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hash::{BuildHasherDefault, Hash};
+use core::iter::{FromIterator, FusedIterator};
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::collections::hash_map::{self, HashMap};
+#[cfg(not(feature = "std"))]
+use hashbrown::hash_map::{self, HashMap};
+
+use crate::tuple::Target;
+use crate::{CloneInPlace, Downcast, IntoBox, TypeIdHasher, TypeTuple};
+
+/// The space a `SmallBox` has for a value inline: two words, aligned as a word.
+///
+/// It’s in an `UnsafeCell` because the value may have interior mutability (a `Cell`, say), and
+/// so be changed through a shared reference to the `SmallBox`.
+type Inline = UnsafeCell<MaybeUninit<[usize; 2]>>;
+
+enum Repr<A: ?Sized> {
+    /// A value that fits, with the means to make a pointer to it into an `A`.
+    Inline {
+        value: Inline,
+        as_dyn_ptr: fn(*mut u8) -> *mut A,
+    },
+    Boxed(Box<A>),
+}
+
+/// Makes a pointer to a `T` in a `SmallBox` into an `A`, for `Repr::Inline`.
+fn as_dyn_ptr<T: IntoBox<A>, A: ?Sized + Downcast>(ptr: *mut u8) -> *mut A {
+    T::as_dyn_ptr(ptr.cast::<T>())
+}
+
+/// A value of type `A`, stored inline if it fits in two words (and is aligned no more strictly
+/// than a word), or boxed if not.
+///
+/// This is what an inline [`Map`] stores its values in: like a `Box<A>`, it dereferences to the
+/// `A` and drops the value when dropped, but it only allocates for values that don’t fit.
+pub struct SmallBox<A: ?Sized>(Repr<A>);
+
+// The `UnsafeCell` makes it not `Sync`, but a value inline is as `Sync` as one in a box.
+unsafe impl<A: ?Sized + Sync> Sync for SmallBox<A> {}
+
+impl<A: ?Sized + Downcast> SmallBox<A> {
+    /// Store `value` inline if it fits, or in a box if not.
+    #[inline]
+    pub fn new<T: IntoBox<A>>(value: T) -> SmallBox<A> {
+        if mem::size_of::<T>() <= mem::size_of::<Inline>() &&
+            mem::align_of::<T>() <= mem::align_of::<Inline>()
+        {
+            let inline = Inline::new(MaybeUninit::uninit());
+            unsafe { inline.get().cast::<T>().write(value) };
+            SmallBox(Repr::Inline {
+                value: inline,
+                as_dyn_ptr: as_dyn_ptr::<T, A>,
+            })
+        } else {
+            SmallBox(Repr::Boxed(value.into_box()))
+        }
+    }
+
+    /// Returns true if the value is stored inline, false if it’s boxed.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+
+    /// Downcast to `&T`, without checking the type matches.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `T` matches the value, on pain of *undefined behaviour*.
+    #[inline]
+    pub unsafe fn downcast_ref_unchecked<T: 'static>(&self) -> &T {
+        match &self.0 {
+            Repr::Inline { value, .. } => &*value.get().cast::<T>(),
+            Repr::Boxed(value) => value.downcast_ref_unchecked(),
+        }
+    }
+
+    /// Downcast to `&mut T`, without checking the type matches.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `T` matches the value, on pain of *undefined behaviour*.
+    #[inline]
+    pub unsafe fn downcast_mut_unchecked<T: 'static>(&mut self) -> &mut T {
+        match &mut self.0 {
+            Repr::Inline { value, .. } => &mut *value.get().cast::<T>(),
+            Repr::Boxed(value) => value.downcast_mut_unchecked(),
+        }
+    }
+
+    /// Move the value out as a `T`, without checking the type matches.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `T` matches the value, on pain of *undefined behaviour*.
+    #[inline]
+    pub unsafe fn downcast_unchecked<T: 'static>(self) -> T {
+        let this = ManuallyDrop::new(self);
+        match &this.0 {
+            Repr::Inline { value, .. } => ptr::read(value.get().cast::<T>()),
+            Repr::Boxed(value) => *ptr::read(value).downcast_unchecked::<T>(),
+        }
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can, and an
+// inline value needs cloning in place.
+impl<A: ?Sized + CloneInPlace> Clone for SmallBox<A> where Box<A>: Clone {
+    fn clone(&self) -> SmallBox<A> {
+        match self.0 {
+            Repr::Inline { as_dyn_ptr, .. } => {
+                let value = Inline::new(MaybeUninit::uninit());
+                // SAFETY: the clone is of the same type as this value, so it fits inline too.
+                unsafe { (**self).clone_in_place(value.get().cast()) };
+                SmallBox(Repr::Inline { value, as_dyn_ptr })
+            },
+            Repr::Boxed(ref value) => SmallBox(Repr::Boxed(value.clone())),
+        }
+    }
+}
+
+/// A boxed value is kept in its box.
+impl<A: ?Sized> From<Box<A>> for SmallBox<A> {
+    #[inline]
+    fn from(value: Box<A>) -> SmallBox<A> {
+        SmallBox(Repr::Boxed(value))
+    }
+}
+
+impl<A: ?Sized> Deref for SmallBox<A> {
+    type Target = A;
+
+    #[inline]
+    fn deref(&self) -> &A {
+        match &self.0 {
+            Repr::Inline { value, as_dyn_ptr } => unsafe {
+                &*as_dyn_ptr(value.get().cast())
+            },
+            Repr::Boxed(value) => value,
+        }
+    }
+}
+
+impl<A: ?Sized> DerefMut for SmallBox<A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut A {
+        match &mut self.0 {
+            Repr::Inline { value, as_dyn_ptr } => unsafe {
+                &mut *as_dyn_ptr(value.get().cast())
+            },
+            Repr::Boxed(value) => value,
+        }
+    }
+}
+
+impl<A: ?Sized> Drop for SmallBox<A> {
+    #[inline]
+    fn drop(&mut self) {
+        // A box drops itself.
+        if let Repr::Inline { value, as_dyn_ptr } = &mut self.0 {
+            unsafe { ptr::drop_in_place(as_dyn_ptr(value.get().cast())) }
+        }
+    }
+}
+
+impl<A: ?Sized + fmt::Debug> fmt::Debug for SmallBox<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Raw access to the underlying `HashMap`, which is the storage behind an inline [`Map`].
+pub type RawMap<A> = HashMap<TypeId, SmallBox<A>, BuildHasherDefault<TypeIdHasher>>;
+
+#[cfg(feature = "std")]
+type RawOccupiedEntry<'a, A> = hash_map::OccupiedEntry<'a, TypeId, SmallBox<A>>;
+#[cfg(not(feature = "std"))]
+type RawOccupiedEntry<'a, A> =
+    hash_map::OccupiedEntry<'a, TypeId, SmallBox<A>, BuildHasherDefault<TypeIdHasher>>;
+
+#[cfg(feature = "std")]
+type RawVacantEntry<'a, A> = hash_map::VacantEntry<'a, TypeId, SmallBox<A>>;
+#[cfg(not(feature = "std"))]
+type RawVacantEntry<'a, A> =
+    hash_map::VacantEntry<'a, TypeId, SmallBox<A>, BuildHasherDefault<TypeIdHasher>>;
+
+/// A collection containing zero or one values for any given type and allowing convenient,
+/// type-safe access to those values, storing small values inline instead of boxing them.
+///
+/// Every [`insert`](crate::Map::insert) into an ordinary [`Map`](crate::Map) allocates a box for
+/// the value. This `Map` instead keeps values that fit in two words (and are aligned no more
+/// strictly than a word) in the hash table itself, in a [`SmallBox`], and only boxes larger ones.
+/// That saves an allocation per small value, in return for slots of three words plus a tag,
+/// where a `Box<A>` takes two.
+///
+/// The value type `A` is as for [`Map`](crate::Map): `Any`, [`CloneAny`](crate::CloneAny),
+/// [`DebugAny`](crate::DebugAny), *&c.*, with `+ Send` and/or `+ Sync`. This has the API of
+/// `Map`, except that values come out of [`drain`](Map::drain) and `into_iter` as `SmallBox`es,
+/// and that there’s no `upcast` (an inline value can only be made into the `A` it was inserted
+/// as) and no recycling mode (there being fewer boxes to recycle). Cloning is as for `Map`, with
+/// a value stored inline cloned in place, through [`CloneInPlace`].
+///
+/// ## Example
+///
+/// ```rust
+/// let mut data = anymap::inline::AnyMap::new();
+/// data.insert(42u32);
+/// data.insert([0u64; 4]);
+/// assert_eq!(data.get::<u32>(), Some(&42));
+/// assert!(data.get_raw::<u32>().unwrap().is_inline());
+/// assert!(!data.get_raw::<[u64; 4]>().unwrap().is_inline());
+/// ```
+pub struct Map<A: ?Sized + Downcast = dyn Any> {
+    raw: RawMap<A>,
+}
+
+/// The most common type of [`Map`]: just using `Any`; <code>[Map]&lt;dyn [Any]&gt;</code>.
+pub type AnyMap = Map<dyn Any>;
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only SmallBox<A> can.
+impl<A: ?Sized + Downcast + CloneInPlace> Clone for Map<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> Map<A> {
+        Map {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+/// Equality doesn’t depend on the order of the items.
+impl<A: ?Sized + Downcast + PartialEq> PartialEq for Map<A> {
+    fn eq(&self, other: &Map<A>) -> bool {
+        self.raw.len() == other.raw.len() &&
+            self.raw.iter().all(|(type_id, value)| {
+                other.raw.get(type_id).is_some_and(|other_value| **value == **other_value)
+            })
+    }
+}
+
+impl<A: ?Sized + Downcast + Eq> Eq for Map<A> {}
+
+/// The hash is independent of the order in which the items are stored.
+impl<A: ?Sized + Downcast + Hash> Hash for Map<A> {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.fingerprint())
+    }
+}
+
+impl<A: ?Sized + Downcast> Default for Map<A> {
+    #[inline]
+    fn default() -> Map<A> {
+        Map::new()
+    }
+}
+
+impl<A: ?Sized + Downcast + fmt::Debug> fmt::Debug for Map<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.raw.iter().map(|(type_id, value)| {
+                (crate::TypeName::new(*type_id, Downcast::type_name(&**value)), value)
+            }))
+            .finish()
+    }
+}
+
+impl<A: ?Sized + Downcast> Map<A> {
+    /// Create an empty collection.
+    #[inline]
+    pub fn new() -> Map<A> {
+        Map {
+            raw: RawMap::with_hasher(Default::default()),
+        }
+    }
+
+    /// Creates an empty collection with the given initial capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Map<A> {
+        Map {
+            raw: RawMap::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    /// Returns the number of elements the collection can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.raw.reserve(additional)
+    }
+
+    /// Shrinks the capacity of the collection as much as possible.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.raw.shrink_to_fit()
+    }
+
+    /// Returns the number of items in the collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns true if there are no items in the collection.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Removes all items from the collection. Keeps the allocated memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.raw.clear()
+    }
+
+    /// Returns a reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.raw.get(&TypeId::of::<T>())
+            .map(|any| unsafe { any.downcast_ref_unchecked::<T>() })
+    }
+
+    /// Returns a mutable reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.raw.get_mut(&TypeId::of::<T>())
+            .map(|any| unsafe { any.downcast_mut_unchecked::<T>() })
+    }
+
+    /// Returns references to the values stored in the collection for each of the types in
+    /// the tuple `T`, if they all exist. See [`crate::Map::get_all`].
+    #[inline]
+    pub fn get_all<T: TypeTuple<A>>(&self) -> Option<T::Refs<'_>> {
+        let raw = &self.raw;
+        // SAFETY: the map’s invariant is that the value’s type matches the key.
+        unsafe { T::get_all(|type_id| raw.get(&type_id).map(|value| &**value)) }
+    }
+
+    /// Returns mutable references to the values stored in the collection for each of the
+    /// types in the tuple `T`, if they all exist. See [`crate::Map::get_many_mut`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn get_many_mut<T: TypeTuple<A>>(&mut self) -> Option<T::RefsMut<'_>> {
+        let raw = &mut self.raw;
+        // SAFETY: the map’s invariant is that the value’s type matches the key, and each
+        // pointer is only handed out once, for the duration of the borrow of self. (Different
+        // keys have different places in the hash table.)
+        unsafe {
+            T::get_many_mut(|type_id| raw.get_mut(&type_id).map(|value| &mut **value as *mut A))
+        }
+    }
+
+    /// Returns the [`SmallBox`] holding the value for the type `T`, if it exists.
+    #[inline]
+    pub fn get_raw<T: IntoBox<A>>(&self) -> Option<&SmallBox<A>> {
+        self.raw.get(&TypeId::of::<T>())
+    }
+
+    /// Sets the value stored in the collection for the type `T`.
+    /// If the collection already had a value of type `T`, that value is returned.
+    /// Otherwise, `None` is returned.
+    ///
+    /// Replacing a value reuses its place, so only inserting a new large value allocates.
+    #[inline]
+    pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        match self.entry::<T>() {
+            Entry::Occupied(mut inner) => Some(inner.insert(value)),
+            Entry::Vacant(inner) => {
+                let _ = inner.insert(value);
+                None
+            },
+        }
+    }
+
+    /// Sets the values stored in the collection for each of the types in the tuple,
+    /// returning the values they replace. See [`crate::Map::insert_all`].
+    #[inline]
+    pub fn insert_all<T: TypeTuple<A>>(&mut self, values: T) -> T::Options {
+        self.reserve(T::LEN);
+        values.insert_all(self)
+    }
+
+    /// Removes the `T` value from the collection,
+    /// returning it if there was one or `None` if there was not.
+    #[inline]
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        self.raw.remove(&TypeId::of::<T>())
+            .map(|any| unsafe { any.downcast_unchecked::<T>() })
+    }
+
+    /// Removes the values of each of the types in the tuple `T` from the collection,
+    /// returning them if they were all there. If any is missing, nothing is removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn remove_all<T: TypeTuple<A>>(&mut self) -> Option<T> {
+        if !self.contains_all::<T>() {
+            return None;
+        }
+        T::remove_all(self)
+    }
+
+    /// Returns true if the collection contains a value of each of the types in the tuple.
+    #[inline]
+    pub fn contains_all<T: TypeTuple<A>>(&self) -> bool {
+        T::contains_all(|type_id| self.raw.contains_key(&type_id))
+    }
+
+    /// Returns true if the collection contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.raw.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Gets the entry for the given type in the collection for in-place manipulation
+    #[inline]
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T> {
+        match self.raw.entry(TypeId::of::<T>()) {
+            hash_map::Entry::Occupied(e) => Entry::Occupied(OccupiedEntry {
+                inner: e,
+                type_: PhantomData,
+            }),
+            hash_map::Entry::Vacant(e) => Entry::Vacant(VacantEntry {
+                inner: e,
+                type_: PhantomData,
+            }),
+        }
+    }
+
+    /// Returns the name of the type of the item stored under `type_id`, if there is such an
+    /// item and its name is known. See [`crate::Map::type_name`].
+    #[inline]
+    pub fn type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.raw.get(&type_id).and_then(|value| Downcast::type_name(&**value))
+    }
+
+    /// An iterator visiting the names of the types in the collection, in arbitrary order.
+    ///
+    /// Items whose names aren’t known (see [`type_name`](Map::type_name)) are skipped.
+    #[inline]
+    pub fn type_names(&self) -> TypeNames<'_, A> {
+        TypeNames {
+            inner: self.raw.iter(),
+        }
+    }
+
+    /// An iterator visiting all items in the collection in arbitrary order,
+    /// yielding each value’s `TypeId` and a reference to the value.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, A> {
+        Iter {
+            inner: self.raw.iter(),
+        }
+    }
+
+    /// An iterator visiting all items in the collection in arbitrary order,
+    /// yielding each value’s `TypeId` and a mutable reference to the value.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, A> {
+        IterMut {
+            inner: self.raw.iter_mut(),
+        }
+    }
+
+    /// Clears the collection, returning all values as an iterator of `SmallBox`es.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// If the returned iterator is dropped before being fully consumed, it drops the
+    /// remaining items.
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, A> {
+        Drain {
+            inner: self.raw.drain(),
+        }
+    }
+
+    /// Retains only the items specified by the predicate.
+    ///
+    /// In other words, remove all items for which `f(type_id, &mut value)` returns
+    /// `false`.
+    #[inline]
+    pub fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        self.raw.retain(|&type_id, value| f(type_id, value))
+    }
+
+    /// Get access to the raw hash map that backs this.
+    ///
+    /// This will seldom be useful, but it’s conceivable that you could wish to iterate
+    /// over all the items in the collection, and this lets you do that.
+    #[inline]
+    pub fn as_raw(&self) -> &RawMap<A> {
+        &self.raw
+    }
+
+    /// Get mutable access to the raw hash map that backs this.
+    ///
+    /// # Safety
+    ///
+    /// If you insert any values to the raw map, the key (a `TypeId`) must match the
+    /// value’s type, or *undefined behaviour* will occur when you access those values.
+    ///
+    /// (*Removing* entries is perfectly safe.)
+    #[inline]
+    pub unsafe fn as_raw_mut(&mut self) -> &mut RawMap<A> {
+        &mut self.raw
+    }
+
+    /// Convert this into the raw hash map that backs this.
+    #[inline]
+    pub fn into_raw(self) -> RawMap<A> {
+        self.raw
+    }
+
+    /// Construct a map from a raw hash map.
+    ///
+    /// # Safety
+    ///
+    /// For all entries in the raw map, the key (a `TypeId`) must match the value’s type,
+    /// or *undefined behaviour* will occur when you access that entry.
+    #[inline]
+    pub unsafe fn from_raw(raw: RawMap<A>) -> Map<A> {
+        Map { raw }
+    }
+}
+
+impl<A: ?Sized + Downcast + Hash> Map<A> {
+    /// Returns a hash of the contents of the collection, independent of the order in which
+    /// the items are stored. See [`crate::Map::fingerprint`].
+    pub fn fingerprint(&self) -> u64 {
+        self.raw.iter().fold(0u64, |fingerprint, (&type_id, value)| {
+            fingerprint.wrapping_add(crate::hash_item(type_id, &**value))
+        })
+    }
+}
+
+impl<A: ?Sized + Downcast> Target<A> for Map<A> {
+    #[inline]
+    fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        Map::insert(self, value)
+    }
+
+    #[inline]
+    fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        Map::remove(self)
+    }
+}
+
+impl<A: ?Sized + Downcast> Extend<SmallBox<A>> for Map<A> {
+    #[inline]
+    fn extend<T: IntoIterator<Item = SmallBox<A>>>(&mut self, iter: T) {
+        for item in iter {
+            let _ = self.raw.insert(Downcast::type_id(&*item), item);
+        }
+    }
+}
+
+/// Boxed values are kept in their boxes.
+impl<A: ?Sized + Downcast> Extend<Box<A>> for Map<A> {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Box<A>>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().map(SmallBox::from))
+    }
+}
+
+impl<A: ?Sized + Downcast> FromIterator<SmallBox<A>> for Map<A> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = SmallBox<A>>>(iter: T) -> Map<A> {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Boxed values are kept in their boxes.
+impl<A: ?Sized + Downcast> FromIterator<Box<A>> for Map<A> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Box<A>>>(iter: T) -> Map<A> {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> IntoIterator for &'a Map<A> {
+    type Item = (TypeId, &'a A);
+    type IntoIter = Iter<'a, A>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, A> {
+        self.iter()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> IntoIterator for &'a mut Map<A> {
+    type Item = (TypeId, &'a mut A);
+    type IntoIter = IterMut<'a, A>;
+
+    #[inline]
+    fn into_iter(self) -> IterMut<'a, A> {
+        self.iter_mut()
+    }
+}
+
+impl<A: ?Sized + Downcast> IntoIterator for Map<A> {
+    type Item = SmallBox<A>;
+    type IntoIter = IntoIter<A>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<A> {
+        IntoIter {
+            inner: self.raw.into_iter(),
+        }
+    }
+}
+
+/// An iterator over the names of the types in an inline `Map`, created by [`Map::type_names`].
+pub struct TypeNames<'a, A: ?Sized + Downcast + 'a> {
+    inner: hash_map::Iter<'a, TypeId, SmallBox<A>>,
+}
+
+impl<'a, A: ?Sized + Downcast> Clone for TypeNames<'a, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        TypeNames {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> Iterator for TypeNames<'a, A> {
+    type Item = &'static str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'static str> {
+        self.inner.by_ref().filter_map(|(_, value)| Downcast::type_name(&**value)).next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> FusedIterator for TypeNames<'a, A> {}
+
+/// An iterator over the items of an inline `Map`, created by [`Map::iter`].
+pub struct Iter<'a, A: ?Sized + Downcast + 'a> {
+    inner: hash_map::Iter<'a, TypeId, SmallBox<A>>,
+}
+
+impl<'a, A: ?Sized + Downcast> Clone for Iter<'a, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> Iterator for Iter<'a, A> {
+    type Item = (TypeId, &'a A);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, &'a A)> {
+        self.inner.next().map(|(&type_id, value)| (type_id, &**value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> ExactSizeIterator for Iter<'a, A> {}
+impl<'a, A: ?Sized + Downcast> FusedIterator for Iter<'a, A> {}
+
+/// A mutable iterator over the items of an inline `Map`, created by [`Map::iter_mut`].
+pub struct IterMut<'a, A: ?Sized + Downcast + 'a> {
+    inner: hash_map::IterMut<'a, TypeId, SmallBox<A>>,
+}
+
+impl<'a, A: ?Sized + Downcast> Iterator for IterMut<'a, A> {
+    type Item = (TypeId, &'a mut A);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, &'a mut A)> {
+        self.inner.next().map(|(&type_id, value)| (type_id, &mut **value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> ExactSizeIterator for IterMut<'a, A> {}
+impl<'a, A: ?Sized + Downcast> FusedIterator for IterMut<'a, A> {}
+
+/// A draining iterator over the items of an inline `Map`, created by [`Map::drain`].
+pub struct Drain<'a, A: ?Sized + Downcast + 'a> {
+    inner: hash_map::Drain<'a, TypeId, SmallBox<A>>,
+}
+
+impl<'a, A: ?Sized + Downcast> Iterator for Drain<'a, A> {
+    type Item = SmallBox<A>;
+
+    #[inline]
+    fn next(&mut self) -> Option<SmallBox<A>> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast> ExactSizeIterator for Drain<'a, A> {}
+impl<'a, A: ?Sized + Downcast> FusedIterator for Drain<'a, A> {}
+
+/// An owning iterator over the items of an inline `Map`, created by its `into_iter` method.
+pub struct IntoIter<A: ?Sized + Downcast> {
+    inner: hash_map::IntoIter<TypeId, SmallBox<A>>,
+}
+
+impl<A: ?Sized + Downcast> Iterator for IntoIter<A> {
+    type Item = SmallBox<A>;
+
+    #[inline]
+    fn next(&mut self) -> Option<SmallBox<A>> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<A: ?Sized + Downcast> ExactSizeIterator for IntoIter<A> {}
+impl<A: ?Sized + Downcast> FusedIterator for IntoIter<A> {}
+
+/// A view into a single occupied location in an inline `Map`.
+pub struct OccupiedEntry<'a, A: ?Sized + Downcast, V: 'a> {
+    inner: RawOccupiedEntry<'a, A>,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single empty location in an inline `Map`.
+pub struct VacantEntry<'a, A: ?Sized + Downcast, V: 'a> {
+    inner: RawVacantEntry<'a, A>,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single location in an inline `Map`, which may be vacant or occupied.
+pub enum Entry<'a, A: ?Sized + Downcast, V: 'a> {
+    /// An occupied Entry
+    Occupied(OccupiedEntry<'a, A, V>),
+    /// A vacant Entry
+    Vacant(VacantEntry<'a, A, V>),
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> Entry<'a, A, V> {
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if
+    /// empty, and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_default(self) -> &'a mut V where V: Default {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(Default::default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts
+    /// into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut inner) => {
+                f(inner.get_mut());
+                Entry::Occupied(inner)
+            },
+            Entry::Vacant(inner) => Entry::Vacant(inner),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> OccupiedEntry<'a, A, V> {
+    /// Gets a reference to the value in the entry
+    #[inline]
+    pub fn get(&self) -> &V {
+        unsafe { self.inner.get().downcast_ref_unchecked() }
+    }
+
+    /// Gets a mutable reference to the value in the entry
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.inner.get_mut().downcast_mut_unchecked() }
+    }
+
+    /// Converts the OccupiedEntry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the collection itself
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { self.inner.into_mut().downcast_mut_unchecked() }
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry, and returns it
+    #[inline]
+    pub fn remove(self) -> V {
+        unsafe { self.inner.remove().downcast_unchecked() }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> VacantEntry<'a, A, V> {
+    /// Sets the value of the entry with the VacantEntry's key,
+    /// and returns a mutable reference to it
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        unsafe { self.inner.insert(SmallBox::new(value)).downcast_mut_unchecked() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::any::type_name;
+    use core::cell::Cell;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use crate::DebugAny;
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, rc::Rc, vec::Vec};
+    #[cfg(feature = "std")]
+    use std::rc::Rc;
+
+    #[derive(Clone, Debug, PartialEq)] struct Small(u32);
+    #[derive(Clone, Debug, PartialEq)] struct Big([u64; 3]);
+    #[derive(Debug, PartialEq)] #[repr(align(32))] struct Aligned(u8);
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Dropper(#[allow(dead_code)] u32);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) {
+            let _ = DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_inline() {
+        let mut map: Map<dyn DebugAny> = Map::new();
+        assert_eq!(map.insert(Small(1)), None);
+        assert_eq!(map.insert(Big([1, 2, 3])), None);
+        assert_eq!(map.insert(Aligned(4)), None);
+        assert_eq!(map.insert("five"), None);
+        assert!(map.get_raw::<Small>().unwrap().is_inline());
+        assert!(map.get_raw::<&str>().unwrap().is_inline());
+        assert!(!map.get_raw::<Big>().unwrap().is_inline());
+        assert!(!map.get_raw::<Aligned>().unwrap().is_inline());
+
+        assert_eq!(map.insert(Small(10)), Some(Small(1)));
+        assert_eq!(map.get::<Small>(), Some(&Small(10)));
+        map.get_mut::<Big>().unwrap().0[0] = 7;
+        assert_eq!(map.get::<Big>(), Some(&Big([7, 2, 3])));
+        assert_eq!(map.remove::<Aligned>(), Some(Aligned(4)));
+        assert!(!map.contains::<Aligned>());
+        assert_eq!(map.len(), 3);
+
+        *map.entry::<Small>().or_insert(Small(0)) = Small(11);
+        assert_eq!(*map.entry::<u8>().and_modify(|v| *v += 1).or_default(), 0);
+        match map.entry::<Small>() {
+            Entry::Occupied(view) => assert_eq!(view.remove(), Small(11)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        let mut debug = map.iter().map(|(_, value)| format!("{:?}", value)).collect::<Vec<_>>();
+        debug.sort();
+        assert_eq!(debug, ["\"five\"", "0", "Big([7, 2, 3])"]);
+        let debug = format!("{:?}", map);
+        assert!(debug.contains("&str") && debug.contains("Big"), "{}", debug);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut map: Map<dyn crate::CloneAny> = Map::new();
+        let _ = map.insert(Small(1));
+        let _ = map.insert(Big([1, 2, 3]));
+        let _ = map.insert(Rc::new(5u8));
+        let clone = map.clone();
+        assert!(clone.get_raw::<Small>().unwrap().is_inline());
+        assert!(!clone.get_raw::<Big>().unwrap().is_inline());
+        assert_eq!(clone.get::<Small>(), Some(&Small(1)));
+        assert_eq!(clone.get::<Big>(), Some(&Big([1, 2, 3])));
+        assert_eq!(Rc::strong_count(map.get::<Rc<u8>>().unwrap()), 2);
+        assert_eq!(clone.type_name(TypeId::of::<Small>()), map.type_name(TypeId::of::<Small>()));
+        drop(map);
+        assert_eq!(Rc::strong_count(clone.get::<Rc<u8>>().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_bulk() {
+        let mut map: Map<dyn DebugAny> = Map::new();
+        assert_eq!(map.insert_all((Small(1), Big([1, 2, 3]), 3u8)), (None, None, None));
+        assert!(map.get_raw::<Small>().unwrap().is_inline());
+        assert_eq!(map.get_all::<(Small, u8)>(), Some((&Small(1), &3)));
+        let (small, byte) = map.get_many_mut::<(Small, u8)>().unwrap();
+        small.0 += 1;
+        *byte += 1;
+        assert!(map.contains_all::<(Small, Big)>());
+        assert_eq!(map.remove_all::<(Small, Aligned)>(), None);
+        assert_eq!(map.remove_all::<(Small, u8)>(), Some((Small(2), 4)));
+        let mut names = map.type_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, [type_name::<Big>()]);
+
+        for (_, value) in &mut map {
+            assert_eq!(format!("{:?}", value), "Big([1, 2, 3])");
+        }
+        let _ = map.insert(Small(5));
+        map.retain(|type_id, _| type_id == TypeId::of::<Small>());
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.type_names().collect::<Vec<_>>(), [type_name::<Small>()]);
+
+        let _ = map.insert(Big([4, 5, 6]));
+        let drained = map.drain().collect::<Vec<_>>();
+        assert!(map.is_empty());
+        assert_eq!(map.type_names().count(), 0);
+        let mut map = drained.into_iter().collect::<Map<dyn DebugAny>>();
+        assert!(map.get_raw::<Small>().unwrap().is_inline());
+        assert_eq!(map.get::<Big>(), Some(&Big([4, 5, 6])));
+        map.extend(Some(Box::new(Small(6)) as Box<dyn DebugAny>));
+        assert!(!map.get_raw::<Small>().unwrap().is_inline());
+        assert_eq!(map.remove::<Small>(), Some(Small(6)));
+        assert_eq!(map.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_interior_mutability() {
+        let mut map = AnyMap::new();
+        let _ = map.insert(Cell::new(1u32));
+        assert!(map.get_raw::<Cell<u32>>().unwrap().is_inline());
+        map.get::<Cell<u32>>().unwrap().set(2);
+        assert_eq!(map.get::<Cell<u32>>().map(Cell::get), Some(2));
+
+        fn assert_sync<T: Sync>(value: T) -> T { value }
+        let mut map = assert_sync(Map::<dyn Any + Send + Sync>::new());
+        let _ = map.insert(AtomicUsize::new(3));
+        map.get::<AtomicUsize>().unwrap().store(4, Ordering::SeqCst);
+        assert_eq!(map.remove::<AtomicUsize>().map(AtomicUsize::into_inner), Some(4));
+    }
+
+    #[test]
+    fn test_inline_drops() {
+        let mut map = AnyMap::new();
+        let _ = map.insert(Dropper(1));
+        assert!(map.get_raw::<Dropper>().unwrap().is_inline());
+        let _ = map.insert(Dropper(2));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        let _ = map.remove::<Dropper>();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        let _ = map.insert(Dropper(3));
+        let _ = map.insert(Big([3, 3, 3]));
+        drop(map);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+
+        drop(SmallBox::<dyn Any>::new(Dropper(4)));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,315 @@
+use core::{any::TypeId, borrow::Borrow, hash::{Hash, Hasher}};
+
+/// The key of an item in a `KeyedAnyMap`, so that `(TypeId, K)` can be looked up by a `(TypeId,
+/// &K)` without cloning the `K`.
+pub(crate) trait KeyPair<K> {
+    fn type_id(&self) -> TypeId;
+    fn key(&self) -> &K;
+}
+
+impl<K> KeyPair<K> for (TypeId, K) {
+    #[inline]
+    fn type_id(&self) -> TypeId { self.0 }
+    #[inline]
+    fn key(&self) -> &K { &self.1 }
+}
+
+impl<K> KeyPair<K> for (TypeId, &K) {
+    #[inline]
+    fn type_id(&self) -> TypeId { self.0 }
+    #[inline]
+    fn key(&self) -> &K { self.1 }
+}
+
+impl<'a, K: 'a> Borrow<dyn KeyPair<K> + 'a> for (TypeId, K) {
+    #[inline]
+    fn borrow(&self) -> &(dyn KeyPair<K> + 'a) {
+        self
+    }
+}
+
+// These must match the implementations for (TypeId, K), which hash and compare field by field.
+impl<'a, K: Hash> Hash for dyn KeyPair<K> + 'a {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state);
+        self.key().hash(state);
+    }
+}
+
+impl<'a, K: PartialEq> PartialEq for dyn KeyPair<K> + 'a {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id() == other.type_id() && self.key() == other.key()
+    }
+}
+
+impl<'a, K: Eq> Eq for dyn KeyPair<K> + 'a {}
+
+/// Generates `KeyedAnyMap` and its iterator, in the same manner as `everything!`.
+macro_rules! keyed_map {
+    ($example_init:literal) => {
+        /// A collection containing zero or one values for any given type *per key*, allowing
+        /// convenient, type-safe access to those values.
+        ///
+        /// This is like a [`Map`] for each key, all in one `HashMap<(TypeId, K), Box<A>>`. The
+        /// value type `A` is as for [`Map`].
+        ///
+        /// Looking up a value by key and type is as fast as in a [`Map`] (though hashing uses the
+        /// `HashMap`’s default hasher, since `TypeIdHasher` can’t hash the keys); but
+        /// [`iter_type`](KeyedAnyMap::iter_type) and [`remove_key`](KeyedAnyMap::remove_key)
+        /// have to look through every item in the collection.
+        ///
+        /// ```rust
+        #[doc = $example_init]
+        /// data.insert(1, 42i32);
+        /// data.insert(1, "one");
+        /// data.insert(2, 43i32);
+        /// assert_eq!(data.get::<i32>(&1), Some(&42));
+        /// assert_eq!(data.get::<&str>(&2), None);
+        /// let mut ints = data.iter_type::<i32>().collect::<Vec<_>>();
+        /// ints.sort();
+        /// assert_eq!(ints, [(&1, &42), (&2, &43)]);
+        /// data.remove_key(&1);
+        /// assert_eq!(data.len(), 1);
+        /// ```
+        pub struct KeyedAnyMap<K, A: ?Sized + Downcast = dyn Any> {
+            raw: HashMap<(TypeId, K), Box<A>>,
+        }
+
+        impl<K: Clone, A: ?Sized + Downcast> Clone for KeyedAnyMap<K, A> where Box<A>: Clone {
+            #[inline]
+            fn clone(&self) -> KeyedAnyMap<K, A> {
+                KeyedAnyMap {
+                    raw: self.raw.clone(),
+                }
+            }
+        }
+
+        impl<K: Hash + Eq + fmt::Debug, A: ?Sized + Downcast + fmt::Debug> fmt::Debug
+            for KeyedAnyMap<K, A>
+        {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.raw.iter().map(|((type_id, key), value)| {
+                        let name = Downcast::type_name(&**value);
+                        ((key, crate::TypeName::new(*type_id, name)), value)
+                    }))
+                    .finish()
+            }
+        }
+
+        impl<K: Hash + Eq, A: ?Sized + Downcast> Default for KeyedAnyMap<K, A> {
+            #[inline]
+            fn default() -> KeyedAnyMap<K, A> {
+                KeyedAnyMap::new()
+            }
+        }
+
+        impl<K: Hash + Eq, A: ?Sized + Downcast> KeyedAnyMap<K, A> {
+            /// Create an empty collection.
+            #[inline]
+            pub fn new() -> KeyedAnyMap<K, A> {
+                KeyedAnyMap {
+                    raw: HashMap::new(),
+                }
+            }
+
+            /// Creates an empty collection with the given initial capacity.
+            #[inline]
+            pub fn with_capacity(capacity: usize) -> KeyedAnyMap<K, A> {
+                KeyedAnyMap {
+                    raw: HashMap::with_capacity(capacity),
+                }
+            }
+
+            /// Returns the number of elements the collection can hold without reallocating.
+            #[inline]
+            pub fn capacity(&self) -> usize {
+                self.raw.capacity()
+            }
+
+            /// Reserves capacity for at least `additional` more elements to be inserted
+            /// in the collection.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the new allocation size overflows `usize`.
+            #[inline]
+            pub fn reserve(&mut self, additional: usize) {
+                self.raw.reserve(additional)
+            }
+
+            /// Shrinks the capacity of the collection as much as possible.
+            #[inline]
+            pub fn shrink_to_fit(&mut self) {
+                self.raw.shrink_to_fit()
+            }
+
+            /// Returns the number of items (counting each key and type pair) in the collection.
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.raw.len()
+            }
+
+            /// Returns true if there are no items in the collection.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.raw.is_empty()
+            }
+
+            /// Removes all items from the collection. Keeps the allocated memory for reuse.
+            #[inline]
+            pub fn clear(&mut self) {
+                self.raw.clear()
+            }
+
+            /// Returns a reference to the value stored in the collection for the type `T` and
+            /// the given key, if it exists.
+            #[inline]
+            pub fn get<T: IntoBox<A>>(&self, key: &K) -> Option<&T> {
+                self.raw.get(&(TypeId::of::<T>(), key) as &dyn crate::keyed::KeyPair<K>)
+                    .map(|any| unsafe { any.downcast_ref_unchecked::<T>() })
+            }
+
+            /// Returns a mutable reference to the value stored in the collection for the type `T`
+            /// and the given key, if it exists.
+            #[inline]
+            pub fn get_mut<T: IntoBox<A>>(&mut self, key: &K) -> Option<&mut T> {
+                self.raw.get_mut(&(TypeId::of::<T>(), key) as &dyn crate::keyed::KeyPair<K>)
+                    .map(|any| unsafe { any.downcast_mut_unchecked::<T>() })
+            }
+
+            /// Sets the value stored in the collection for the type `T` and the given key.
+            /// If the collection already had a value there, that value is returned.
+            /// Otherwise, `None` is returned.
+            #[inline]
+            pub fn insert<T: IntoBox<A>>(&mut self, key: K, value: T) -> Option<T> {
+                self.raw.insert((TypeId::of::<T>(), key), value.into_box())
+                    .map(|any| unsafe { *any.downcast_unchecked::<T>() })
+            }
+
+            /// Removes the `T` value for the given key from the collection,
+            /// returning it if there was one or `None` if there was not.
+            #[inline]
+            pub fn remove<T: IntoBox<A>>(&mut self, key: &K) -> Option<T> {
+                self.raw.remove(&(TypeId::of::<T>(), key) as &dyn crate::keyed::KeyPair<K>)
+                    .map(|any| *unsafe { any.downcast_unchecked::<T>() })
+            }
+
+            /// Returns true if the collection contains a value of type `T` for the given key.
+            #[inline]
+            pub fn contains<T: IntoBox<A>>(&self, key: &K) -> bool {
+                self.raw.contains_key(&(TypeId::of::<T>(), key) as &dyn crate::keyed::KeyPair<K>)
+            }
+
+            /// Removes the values of every type for the given key.
+            ///
+            /// This has to look through every item in the collection.
+            #[inline]
+            pub fn remove_key(&mut self, key: &K) {
+                self.raw.retain(|(_, k), _| k != key)
+            }
+
+            /// An iterator visiting every key with a value of type `T`, in arbitrary order,
+            /// yielding the key and a reference to the value.
+            ///
+            /// This has to look through every item in the collection.
+            #[inline]
+            pub fn iter_type<T: IntoBox<A>>(&self) -> IterType<'_, K, A, T> {
+                IterType {
+                    inner: self.raw.iter(),
+                    type_: PhantomData,
+                }
+            }
+        }
+
+        /// An iterator over the values of one type in a `KeyedAnyMap`, and their keys.
+        pub struct IterType<'a, K, A: ?Sized + Downcast, T> {
+            inner: hash_map::Iter<'a, (TypeId, K), Box<A>>,
+            type_: PhantomData<&'a T>,
+        }
+
+        impl<'a, K, A: ?Sized + Downcast, T> Clone for IterType<'a, K, A, T> {
+            #[inline]
+            fn clone(&self) -> Self {
+                IterType {
+                    inner: self.inner.clone(),
+                    type_: PhantomData,
+                }
+            }
+        }
+
+        impl<'a, K, A: ?Sized + Downcast, T: IntoBox<A>> Iterator for IterType<'a, K, A, T> {
+            type Item = (&'a K, &'a T);
+
+            #[inline]
+            fn next(&mut self) -> Option<(&'a K, &'a T)> {
+                self.inner
+                    .by_ref()
+                    .find(|((type_id, _), _)| *type_id == TypeId::of::<T>())
+                    .map(|((_, key), any)| (key, unsafe { any.downcast_ref_unchecked::<T>() }))
+            }
+
+            #[inline]
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (0, self.inner.size_hint().1)
+            }
+        }
+
+        impl<'a, K, A: ?Sized + Downcast, T: IntoBox<A>> FusedIterator for IterType<'a, K, A, T> {}
+
+        #[cfg(test)]
+        mod keyed_tests {
+            use crate::{CloneAny, DebugAny};
+            use super::*;
+            #[cfg(not(feature = "std"))]
+            use alloc::{format, string::{String, ToString}, vec::Vec};
+
+            #[derive(Clone, Debug, PartialEq)] struct Session(u32);
+            #[derive(Clone, Debug, PartialEq)] struct Quota(u32);
+
+            #[test]
+            fn test_keyed() {
+                let mut map = KeyedAnyMap::<String, dyn CloneAny>::new();
+                assert_eq!(map.insert("alice".to_string(), Session(1)), None);
+                assert_eq!(map.insert("alice".to_string(), Quota(10)), None);
+                assert_eq!(map.insert("bob".to_string(), Session(2)), None);
+                assert_eq!(map.insert("bob".to_string(), Session(3)), Some(Session(2)));
+                assert_eq!(map.len(), 3);
+
+                let alice = "alice".to_string();
+                let bob = "bob".to_string();
+                assert_eq!(map.get::<Session>(&alice), Some(&Session(1)));
+                assert_eq!(map.get::<Quota>(&alice), Some(&Quota(10)));
+                assert_eq!(map.get::<Quota>(&bob), None);
+                assert!(map.contains::<Session>(&bob));
+                map.get_mut::<Quota>(&alice).unwrap().0 += 5;
+
+                let mut sessions = map.iter_type::<Session>().collect::<Vec<_>>();
+                sessions.sort_by_key(|&(key, _)| key);
+                assert_eq!(sessions, [(&alice, &Session(1)), (&bob, &Session(3))]);
+                assert_eq!(map.iter_type::<Quota>().collect::<Vec<_>>(), [(&alice, &Quota(15))]);
+
+                let clone = map.clone();
+                map.remove_key(&alice);
+                assert_eq!(map.len(), 1);
+                assert_eq!(map.get::<Session>(&alice), None);
+                assert_eq!(map.remove::<Session>(&bob), Some(Session(3)));
+                assert!(map.is_empty());
+                assert_eq!(clone.len(), 3);
+                assert_eq!(clone.get::<Quota>(&alice), Some(&Quota(15)));
+            }
+
+            #[test]
+            fn test_debug() {
+                let mut map = KeyedAnyMap::<u8, dyn DebugAny>::new();
+                let _ = map.insert(1, Session(2));
+                assert_eq!(
+                    format!("{:?}", map),
+                    format!("{{(1, {}): Session(2)}}", core::any::type_name::<Session>()),
+                );
+            }
+        }
+    };
+}
@@ -1,21 +1,56 @@
 //! This crate provides a safe and convenient store for one value of each type.
 //!
-//! Your starting point is [`Map`]. It has an example.
+#![cfg_attr(feature = "alloc", doc = "
+Your starting point is [`Map`]. It has an example.
+
+A `Map` keeps its values in a [`storage`] of your choosing. By default that’s a hash map, but
+the [`btree`] module has a `Map` that keeps its items in order of `TypeId`, and (with std or
+hashbrown) the `indexed` module has one that keeps them in insertion order, the `adaptive`
+module one that searches an inline array while it holds only a few, and the `slab` module one
+that hands out generational handles for getting at its items without a lookup. You can also
+implement [`storage::RawStorage`] for your own collection. Also with std or hashbrown, the
+`inline` module has a separate `Map` that stores small values in the hash table, not boxed.
+The `arena` module has another, which allocates its values in an arena to free them all at once.
+[`ThinMap`] is a `Map` behind one pointer, which isn’t allocated until something is inserted.
+
+For when there’s no allocator at all, there’s [`StaticAnyMap`], which stores a few values inline.
+")]
+#![cfg_attr(not(feature = "alloc"), doc = "
+Without the alloc feature, as in this build, there’s no `Map`, since it boxes its values, but
+there’s [`StaticAnyMap`], which stores a few values inline without allocating.
+")]
 //!
 //! # Cargo features
 //!
-//! This crate has two independent features, each of which provides an implementation providing
-//! types `Map`, `AnyMap`, `OccupiedEntry`, `VacantEntry`, `Entry` and `RawMap`:
+//! This crate has two independent features, each of which provides a hash map implementation for
+//! `Map`, with types `Map`, `AnyMap`, `OccupiedEntry`, `VacantEntry`, `Entry` and `RawMap`, and
+//! `AnyMultiMap` (for any number of values of each type), `TypeMap` (keyed by a [`Key`] type
+//! rather than the value’s type), both with their own entry types, and `KeyedAnyMap` (for one
+//! value of each type per key):
 //!
 #![cfg_attr(feature = "std", doc = " - **std** (default, *enabled* in this build):")]
 #![cfg_attr(not(feature = "std"), doc = " - **std** (default, *disabled* in this build):")]
 //!   an implementation using `std::collections::hash_map`, placed in the crate root
-//!   (e.g. `anymap::AnyMap`).
+//!   (e.g. `anymap::AnyMap`). This is what `Map` uses by default.
 //!
 #![cfg_attr(feature = "hashbrown", doc = " - **hashbrown** (optional; *enabled* in this build):")]
 #![cfg_attr(not(feature = "hashbrown"), doc = " - **hashbrown** (optional; *disabled* in this build):")]
 //!   an implementation using `alloc` and `hashbrown::hash_map`, placed in a module `hashbrown`
-//!   (e.g. `anymap::hashbrown::AnyMap`).
+//!   (e.g. `anymap::hashbrown::AnyMap`). Without std, this is what `Map` uses by default.
+//!
+//! Both imply a third feature:
+//!
+#![cfg_attr(feature = "alloc", doc = " - **alloc** (implied by either; *enabled* in this build):")]
+#![cfg_attr(not(feature = "alloc"), doc = " - **alloc** (implied by either; *disabled* in this build):")]
+//!   everything that needs an allocator but not a hash map. With neither std nor hashbrown,
+//!   `Map` (with `AnyMap`, its entries and iterators, and its whole API) is still there in the
+//!   crate root, using `storage::SortedVec`, a `Vec` kept sorted by `TypeId` and searched by
+//!   binary search; that `Vec` and the boxed values are all a `Map` allocates (unless it’s
+//!   recycling). `AnyMultiMap`, `TypeMap` and `KeyedAnyMap` need a hash map, and so aren’t
+//!   available.
+//!
+//! Without any of them, only `core` is needed: what’s left is [`StaticAnyMap`] and
+//! [`TypeIdHasher`].
 
 #![warn(missing_docs, unused_results)]
 
@@ -24,559 +59,171 @@
 use core::convert::TryInto;
 use core::hash::Hasher;
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(not(feature = "std"), any(feature = "alloc", test)))]
 extern crate alloc;
 
-pub use crate::any::CloneAny;
+#[cfg(feature = "alloc")]
+pub use crate::any::{BoxFrom, CloneInPlace, Downcast, IntoBox, Named, Upcast};
+#[cfg(feature = "alloc")]
+pub use crate::tuple::TypeTuple;
+pub use crate::type_map::Key;
+#[cfg(feature = "alloc")]
+pub use crate::any::{CloneAny, CloneDebugAny, CloneEqAny, CloneHashAny, DebugAny, EqAny, HashAny};
+
+#[cfg(feature = "alloc")]
+pub use crate::map::{AnyMap, Drain, Entry, IntoIter, Iter, IterMut, Map, OccupiedEntry};
+#[cfg(feature = "alloc")]
+pub use crate::map::{TypeNames, VacantEntry};
+#[cfg(feature = "alloc")]
+pub use crate::thin::{ThinDrain, ThinIntoIter, ThinIter, ThinIterMut, ThinMap, ThinTypeNames};
+
+pub use crate::static_map::{InsertError, OccupiedStaticEntry, StaticAnyMap, StaticEntry};
+pub use crate::static_map::VacantStaticEntry;
+
+/// Defines aliases for `Map` and its associated types with the given storage, for a module that
+/// provides storage.
+#[cfg(feature = "alloc")]
+macro_rules! map_aliases {
+    ($(#[$map_attr:meta])* $raw:ident) => {
+        $(#[$map_attr])*
+        pub type Map<A = dyn core::any::Any> = crate::Map<A, $raw<A>>;
+
+        /// The most common type of [`Map`]: just using `Any`;
+        /// <code>[Map]&lt;dyn [Any](core::any::Any)&gt;</code>.
+        pub type AnyMap = Map<dyn core::any::Any>;
+
+        /// A view into a single location in a [`Map`], which may be vacant or occupied.
+        pub type Entry<'a, A, V> = crate::Entry<'a, A, V, $raw<A>>;
+
+        /// A view into a single occupied location in a [`Map`].
+        pub type OccupiedEntry<'a, A, V> = crate::OccupiedEntry<'a, A, V, $raw<A>>;
 
+        /// A view into a single empty location in a [`Map`].
+        pub type VacantEntry<'a, A, V> = crate::VacantEntry<'a, A, V, $raw<A>>;
+
+        /// An iterator over the items of a [`Map`].
+        pub type Iter<'a, A> = crate::Iter<'a, A, $raw<A>>;
+
+        /// A mutable iterator over the items of a [`Map`].
+        pub type IterMut<'a, A> = crate::IterMut<'a, A, $raw<A>>;
+
+        /// A draining iterator over the items of a [`Map`].
+        pub type Drain<'a, A> = crate::Drain<'a, A, $raw<A>>;
+
+        /// An owning iterator over the items of a [`Map`].
+        pub type IntoIter<A> = crate::IntoIter<A, $raw<A>>;
+
+        /// An iterator over the names of the types in a [`Map`].
+        pub type TypeNames<'a, A> = crate::TypeNames<'a, A, $raw<A>>;
+    };
+}
+
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub mod adaptive;
+#[cfg(feature = "alloc")]
 mod any;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub mod arena;
+#[cfg(feature = "alloc")]
+pub mod btree;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub mod indexed;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub mod inline;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+#[macro_use]
+mod keyed;
+#[cfg(feature = "alloc")]
+mod map;
+#[macro_use]
+mod multi;
+#[cfg(feature = "alloc")]
+mod recycle;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub mod slab;
+mod static_map;
+#[cfg(feature = "alloc")]
+pub mod storage;
+#[cfg(feature = "alloc")]
+mod thin;
+#[cfg(feature = "alloc")]
+mod tuple;
+#[macro_use]
+mod type_map;
+
+// Things that the expansion of `impl_any_trait!` needs, wherever it’s used.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub mod __private {
+    pub use core::any::{Any, TypeId};
+    #[cfg(not(feature = "std"))]
+    pub use alloc::boxed::Box;
+    #[cfg(feature = "std")]
+    pub use std::boxed::Box;
+}
 
 #[cfg(any(feature = "std", feature = "hashbrown"))]
 macro_rules! everything {
-    ($example_init:literal, $($parent:ident)::+ $(, $entry_generics:ty)?) => {
+    (
+        $multi_example_init:literal,
+        $type_map_example_init:literal,
+        $keyed_example_init:literal,
+        $($parent:ident)::+ $(, $entry_generics:ty)?
+    ) => {
         use core::any::{Any, TypeId};
-        use core::hash::BuildHasherDefault;
+        use core::fmt;
+        use core::hash::{BuildHasherDefault, Hash};
+        use core::iter::FusedIterator;
         use core::marker::PhantomData;
 
         #[cfg(not(feature = "std"))]
-        use alloc::boxed::Box;
+        use alloc::{boxed::Box, vec, vec::Vec};
 
         use ::$($parent)::+::hash_map::{self, HashMap};
 
-        use crate::any::{Downcast, IntoBox};
-
-        /// Raw access to the underlying `HashMap`.
+        /// Raw access to the underlying `HashMap`, which is also the storage behind a `Map`.
         ///
         /// This alias is provided for convenience because of the ugly third generic parameter.
         pub type RawMap<A> = HashMap<TypeId, Box<A>, BuildHasherDefault<TypeIdHasher>>;
 
-        /// A collection containing zero or one values for any given type and allowing convenient,
-        /// type-safe access to those values.
-        ///
-        /// The type parameter `A` allows you to use a different value type; normally you will want
-        /// it to be `core::any::Any` (also known as `std::any::Any`), but there are other choices:
-        ///
-        /// - If you want the entire map to be cloneable, use `CloneAny` instead of `Any`; with
-        ///   that, you can only add types that implement `Clone` to the map.
-        /// - You can add on `+ Send` or `+ Send + Sync` (e.g. `Map<dyn Any + Send>`) to add those
-        ///   auto traits.
-        ///
-        /// Cumulatively, there are thus six forms of map:
-        ///
-        /// - <code>[Map]&lt;dyn [core::any::Any]&gt;</code>,
-        ///   also spelled [`AnyMap`] for convenience.
-        /// - <code>[Map]&lt;dyn [core::any::Any] + Send&gt;</code>
-        /// - <code>[Map]&lt;dyn [core::any::Any] + Send + Sync&gt;</code>
-        /// - <code>[Map]&lt;dyn [CloneAny]&gt;</code>
-        /// - <code>[Map]&lt;dyn [CloneAny] + Send&gt;</code>
-        /// - <code>[Map]&lt;dyn [CloneAny] + Send + Sync&gt;</code>
-        ///
-        /// ## Example
-        ///
-        /// (Here using the [`AnyMap`] convenience alias; the first line could use
-        /// <code>[anymap::Map][Map]::&lt;[core::any::Any]&gt;::new()</code> instead if desired.)
-        ///
-        /// ```rust
-        #[doc = $example_init]
-        /// assert_eq!(data.get(), None::<&i32>);
-        /// data.insert(42i32);
-        /// assert_eq!(data.get(), Some(&42i32));
-        /// data.remove::<i32>();
-        /// assert_eq!(data.get::<i32>(), None);
-        ///
-        /// #[derive(Clone, PartialEq, Debug)]
-        /// struct Foo {
-        ///     str: String,
-        /// }
-        ///
-        /// assert_eq!(data.get::<Foo>(), None);
-        /// data.insert(Foo { str: format!("foo") });
-        /// assert_eq!(data.get(), Some(&Foo { str: format!("foo") }));
-        /// data.get_mut::<Foo>().map(|foo| foo.str.push('t'));
-        /// assert_eq!(&*data.get::<Foo>().unwrap().str, "foot");
-        /// ```
-        ///
-        /// Values containing non-static references are not permitted.
-        #[derive(Debug)]
-        pub struct Map<A: ?Sized + Downcast = dyn Any> {
-            raw: RawMap<A>,
-        }
+        multi_map!($multi_example_init $(, $entry_generics)?);
 
-        // #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
-        impl<A: ?Sized + Downcast> Clone for Map<A> where Box<A>: Clone {
-            #[inline]
-            fn clone(&self) -> Map<A> {
-                Map {
-                    raw: self.raw.clone(),
-                }
-            }
-        }
+        type_map!($type_map_example_init $(, $entry_generics)?);
 
-        /// The most common type of `Map`: just using `Any`; <code>[Map]&lt;dyn [Any]&gt;</code>.
-        ///
-        /// Why is this a separate type alias rather than a default value for `Map<A>`?
-        /// `Map::new()` doesn’t seem to be happy to infer that it should go with the default
-        /// value. It’s a bit sad, really. Ah well, I guess this approach will do.
-        pub type AnyMap = Map<dyn Any>;
-
-        impl<A: ?Sized + Downcast> Default for Map<A> {
-            #[inline]
-            fn default() -> Map<A> {
-                Map::new()
-            }
-        }
-
-        impl<A: ?Sized + Downcast> Map<A> {
-            /// Create an empty collection.
-            #[inline]
-            pub fn new() -> Map<A> {
-                Map {
-                    raw: RawMap::with_hasher(Default::default()),
-                }
-            }
-
-            /// Creates an empty collection with the given initial capacity.
-            #[inline]
-            pub fn with_capacity(capacity: usize) -> Map<A> {
-                Map {
-                    raw: RawMap::with_capacity_and_hasher(capacity, Default::default()),
-                }
-            }
-
-            /// Returns the number of elements the collection can hold without reallocating.
-            #[inline]
-            pub fn capacity(&self) -> usize {
-                self.raw.capacity()
-            }
-
-            /// Reserves capacity for at least `additional` more elements to be inserted
-            /// in the collection. The collection may reserve more space to avoid
-            /// frequent reallocations.
-            ///
-            /// # Panics
-            ///
-            /// Panics if the new allocation size overflows `usize`.
-            #[inline]
-            pub fn reserve(&mut self, additional: usize) {
-                self.raw.reserve(additional)
-            }
-
-            /// Shrinks the capacity of the collection as much as possible. It will drop
-            /// down as much as possible while maintaining the internal rules
-            /// and possibly leaving some space in accordance with the resize policy.
-            #[inline]
-            pub fn shrink_to_fit(&mut self) {
-                self.raw.shrink_to_fit()
-            }
-
-            // Additional stable methods (as of 1.60.0-nightly) that could be added:
-            // try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError>    (1.57.0)
-            // shrink_to(&mut self, min_capacity: usize)                                   (1.56.0)
-
-            /// Returns the number of items in the collection.
-            #[inline]
-            pub fn len(&self) -> usize {
-                self.raw.len()
-            }
-
-            /// Returns true if there are no items in the collection.
-            #[inline]
-            pub fn is_empty(&self) -> bool {
-                self.raw.is_empty()
-            }
-
-            /// Removes all items from the collection. Keeps the allocated memory for reuse.
-            #[inline]
-            pub fn clear(&mut self) {
-                self.raw.clear()
-            }
-
-            /// Returns a reference to the value stored in the collection for the type `T`,
-            /// if it exists.
-            #[inline]
-            pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
-                self.raw.get(&TypeId::of::<T>())
-                    .map(|any| unsafe { any.downcast_ref_unchecked::<T>() })
-            }
-
-            /// Returns a mutable reference to the value stored in the collection for the type `T`,
-            /// if it exists.
-            #[inline]
-            pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
-                self.raw.get_mut(&TypeId::of::<T>())
-                    .map(|any| unsafe { any.downcast_mut_unchecked::<T>() })
-            }
-
-            /// Sets the value stored in the collection for the type `T`.
-            /// If the collection already had a value of type `T`, that value is returned.
-            /// Otherwise, `None` is returned.
-            #[inline]
-            pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
-                self.raw.insert(TypeId::of::<T>(), value.into_box())
-                    .map(|any| unsafe { *any.downcast_unchecked::<T>() })
-            }
-
-            // rustc 1.60.0-nightly has another method try_insert that would be nice when stable.
-
-            /// Removes the `T` value from the collection,
-            /// returning it if there was one or `None` if there was not.
-            #[inline]
-            pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
-                self.raw.remove(&TypeId::of::<T>())
-                    .map(|any| *unsafe { any.downcast_unchecked::<T>() })
-            }
-
-            /// Returns true if the collection contains a value of type `T`.
-            #[inline]
-            pub fn contains<T: IntoBox<A>>(&self) -> bool {
-                self.raw.contains_key(&TypeId::of::<T>())
-            }
-
-            /// Gets the entry for the given type in the collection for in-place manipulation
-            #[inline]
-            pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<A, T> {
-                match self.raw.entry(TypeId::of::<T>()) {
-                    hash_map::Entry::Occupied(e) => Entry::Occupied(OccupiedEntry {
-                        inner: e,
-                        type_: PhantomData,
-                    }),
-                    hash_map::Entry::Vacant(e) => Entry::Vacant(VacantEntry {
-                        inner: e,
-                        type_: PhantomData,
-                    }),
-                }
-            }
-
-            /// Get access to the raw hash map that backs this.
-            ///
-            /// This will seldom be useful, but it’s conceivable that you could wish to iterate
-            /// over all the items in the collection, and this lets you do that.
-            #[inline]
-            pub fn as_raw(&self) -> &RawMap<A> {
-                &self.raw
-            }
-
-            /// Get mutable access to the raw hash map that backs this.
-            ///
-            /// This will seldom be useful, but it’s conceivable that you could wish to iterate
-            /// over all the items in the collection mutably, or drain or something, or *possibly*
-            /// even batch insert, and this lets you do that.
-            ///
-            /// # Safety
-            ///
-            /// If you insert any values to the raw map, the key (a `TypeId`) must match the
-            /// value’s type, or *undefined behaviour* will occur when you access those values.
-            ///
-            /// (*Removing* entries is perfectly safe.)
-            #[inline]
-            pub unsafe fn as_raw_mut(&mut self) -> &mut RawMap<A> {
-                &mut self.raw
-            }
-
-            /// Convert this into the raw hash map that backs this.
-            ///
-            /// This will seldom be useful, but it’s conceivable that you could wish to consume all
-            /// the items in the collection and do *something* with some or all of them, and this
-            /// lets you do that, without the `unsafe` that `.as_raw_mut().drain()` would require.
-            #[inline]
-            pub fn into_raw(self) -> RawMap<A> {
-                self.raw
-            }
-
-            /// Construct a map from a collection of raw values.
-            ///
-            /// You know what? I can’t immediately think of any legitimate use for this, especially
-            /// because of the requirement of the `BuildHasherDefault<TypeIdHasher>` generic in the
-            /// map.
-            ///
-            /// Perhaps this will be most practical as `unsafe { Map::from_raw(iter.collect()) }`,
-            /// `iter` being an iterator over `(TypeId, Box<A>)` pairs. Eh, this method provides
-            /// symmetry with `into_raw`, so I don’t care if literally no one ever uses it. I’m not
-            /// even going to write a test for it, it’s so trivial.
-            ///
-            /// # Safety
-            ///
-            /// For all entries in the raw map, the key (a `TypeId`) must match the value’s type,
-            /// or *undefined behaviour* will occur when you access that entry.
-            #[inline]
-            pub unsafe fn from_raw(raw: RawMap<A>) -> Map<A> {
-                Self { raw }
-            }
-        }
-
-        impl<A: ?Sized + Downcast> Extend<Box<A>> for Map<A> {
-            #[inline]
-            fn extend<T: IntoIterator<Item = Box<A>>>(&mut self, iter: T) {
-                for item in iter {
-                    let _ = self.raw.insert(Downcast::type_id(&*item), item);
-                }
-            }
-        }
-
-        /// A view into a single occupied location in an `Map`.
-        pub struct OccupiedEntry<'a, A: ?Sized + Downcast, V: 'a> {
-            inner: hash_map::OccupiedEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
-            type_: PhantomData<V>,
-        }
-
-        /// A view into a single empty location in an `Map`.
-        pub struct VacantEntry<'a, A: ?Sized + Downcast, V: 'a> {
-            inner: hash_map::VacantEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
-            type_: PhantomData<V>,
-        }
-
-        /// A view into a single location in an `Map`, which may be vacant or occupied.
-        pub enum Entry<'a, A: ?Sized + Downcast, V: 'a> {
-            /// An occupied Entry
-            Occupied(OccupiedEntry<'a, A, V>),
-            /// A vacant Entry
-            Vacant(VacantEntry<'a, A, V>),
-        }
-
-        impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> Entry<'a, A, V> {
-            /// Ensures a value is in the entry by inserting the default if empty, and returns
-            /// a mutable reference to the value in the entry.
-            #[inline]
-            pub fn or_insert(self, default: V) -> &'a mut V {
-                match self {
-                    Entry::Occupied(inner) => inner.into_mut(),
-                    Entry::Vacant(inner) => inner.insert(default),
-                }
-            }
-
-            /// Ensures a value is in the entry by inserting the result of the default function if
-            /// empty, and returns a mutable reference to the value in the entry.
-            #[inline]
-            pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
-                match self {
-                    Entry::Occupied(inner) => inner.into_mut(),
-                    Entry::Vacant(inner) => inner.insert(default()),
-                }
-            }
-
-            /// Ensures a value is in the entry by inserting the default value if empty,
-            /// and returns a mutable reference to the value in the entry.
-            #[inline]
-            pub fn or_default(self) -> &'a mut V where V: Default {
-                match self {
-                    Entry::Occupied(inner) => inner.into_mut(),
-                    Entry::Vacant(inner) => inner.insert(Default::default()),
-                }
-            }
-
-            /// Provides in-place mutable access to an occupied entry before any potential inserts
-            /// into the map.
-            #[inline]
-            pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
-                match self {
-                    Entry::Occupied(mut inner) => {
-                        f(inner.get_mut());
-                        Entry::Occupied(inner)
-                    },
-                    Entry::Vacant(inner) => Entry::Vacant(inner),
-                }
-            }
-
-            // Additional stable methods (as of 1.60.0-nightly) that could be added:
-            // insert_entry(self, value: V) -> OccupiedEntry<'a, K, V>                     (1.59.0)
-        }
+        keyed_map!($keyed_example_init);
+    };
+}
 
-        impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> OccupiedEntry<'a, A, V> {
-            /// Gets a reference to the value in the entry
-            #[inline]
-            pub fn get(&self) -> &V {
-                unsafe { self.inner.get().downcast_ref_unchecked() }
-            }
-
-            /// Gets a mutable reference to the value in the entry
-            #[inline]
-            pub fn get_mut(&mut self) -> &mut V {
-                unsafe { self.inner.get_mut().downcast_mut_unchecked() }
-            }
-
-            /// Converts the OccupiedEntry into a mutable reference to the value in the entry
-            /// with a lifetime bound to the collection itself
-            #[inline]
-            pub fn into_mut(self) -> &'a mut V {
-                unsafe { self.inner.into_mut().downcast_mut_unchecked() }
-            }
-
-            /// Sets the value of the entry, and returns the entry's old value
-            #[inline]
-            pub fn insert(&mut self, value: V) -> V {
-                unsafe { *self.inner.insert(value.into_box()).downcast_unchecked() }
-            }
-
-            /// Takes the value out of the entry, and returns it
-            #[inline]
-            pub fn remove(self) -> V {
-                unsafe { *self.inner.remove().downcast_unchecked() }
-            }
-        }
+/// A `Debug` rendering of a type: its name if known, or failing that its `TypeId`.
+#[cfg(feature = "alloc")]
+struct TypeName {
+    type_id: core::any::TypeId,
+    name: Option<&'static str>,
+}
 
-        impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> VacantEntry<'a, A, V> {
-            /// Sets the value of the entry with the VacantEntry's key,
-            /// and returns a mutable reference to it
-            #[inline]
-            pub fn insert(self, value: V) -> &'a mut V {
-                unsafe { self.inner.insert(value.into_box()).downcast_mut_unchecked() }
-            }
-        }
+#[cfg(feature = "alloc")]
+impl TypeName {
+    #[inline]
+    fn new(type_id: core::any::TypeId, name: Option<&'static str>) -> TypeName {
+        TypeName { type_id, name }
+    }
+}
 
-        #[cfg(test)]
-        mod tests {
-            use crate::CloneAny;
-            use super::*;
-
-            #[derive(Clone, Debug, PartialEq)] struct A(i32);
-            #[derive(Clone, Debug, PartialEq)] struct B(i32);
-            #[derive(Clone, Debug, PartialEq)] struct C(i32);
-            #[derive(Clone, Debug, PartialEq)] struct D(i32);
-            #[derive(Clone, Debug, PartialEq)] struct E(i32);
-            #[derive(Clone, Debug, PartialEq)] struct F(i32);
-            #[derive(Clone, Debug, PartialEq)] struct J(i32);
-
-            macro_rules! test_entry {
-                ($name:ident, $init:ty) => {
-                    #[test]
-                    fn $name() {
-                        let mut map = <$init>::new();
-                        assert_eq!(map.insert(A(10)), None);
-                        assert_eq!(map.insert(B(20)), None);
-                        assert_eq!(map.insert(C(30)), None);
-                        assert_eq!(map.insert(D(40)), None);
-                        assert_eq!(map.insert(E(50)), None);
-                        assert_eq!(map.insert(F(60)), None);
-
-                        // Existing key (insert)
-                        match map.entry::<A>() {
-                            Entry::Vacant(_) => unreachable!(),
-                            Entry::Occupied(mut view) => {
-                                assert_eq!(view.get(), &A(10));
-                                assert_eq!(view.insert(A(100)), A(10));
-                            }
-                        }
-                        assert_eq!(map.get::<A>().unwrap(), &A(100));
-                        assert_eq!(map.len(), 6);
-
-
-                        // Existing key (update)
-                        match map.entry::<B>() {
-                            Entry::Vacant(_) => unreachable!(),
-                            Entry::Occupied(mut view) => {
-                                let v = view.get_mut();
-                                let new_v = B(v.0 * 10);
-                                *v = new_v;
-                            }
-                        }
-                        assert_eq!(map.get::<B>().unwrap(), &B(200));
-                        assert_eq!(map.len(), 6);
-
-
-                        // Existing key (remove)
-                        match map.entry::<C>() {
-                            Entry::Vacant(_) => unreachable!(),
-                            Entry::Occupied(view) => {
-                                assert_eq!(view.remove(), C(30));
-                            }
-                        }
-                        assert_eq!(map.get::<C>(), None);
-                        assert_eq!(map.len(), 5);
-
-
-                        // Inexistent key (insert)
-                        match map.entry::<J>() {
-                            Entry::Occupied(_) => unreachable!(),
-                            Entry::Vacant(view) => {
-                                assert_eq!(*view.insert(J(1000)), J(1000));
-                            }
-                        }
-                        assert_eq!(map.get::<J>().unwrap(), &J(1000));
-                        assert_eq!(map.len(), 6);
-
-                        // Entry.or_insert on existing key
-                        map.entry::<B>().or_insert(B(71)).0 += 1;
-                        assert_eq!(map.get::<B>().unwrap(), &B(201));
-                        assert_eq!(map.len(), 6);
-
-                        // Entry.or_insert on nonexisting key
-                        map.entry::<C>().or_insert(C(300)).0 += 1;
-                        assert_eq!(map.get::<C>().unwrap(), &C(301));
-                        assert_eq!(map.len(), 7);
-                    }
-                }
-            }
-
-            test_entry!(test_entry_any, AnyMap);
-            test_entry!(test_entry_cloneany, Map<dyn CloneAny>);
-
-            #[test]
-            fn test_default() {
-                let map: AnyMap = Default::default();
-                assert_eq!(map.len(), 0);
-            }
-
-            #[test]
-            fn test_clone() {
-                let mut map: Map<dyn CloneAny> = Map::new();
-                let _ = map.insert(A(1));
-                let _ = map.insert(B(2));
-                let _ = map.insert(D(3));
-                let _ = map.insert(E(4));
-                let _ = map.insert(F(5));
-                let _ = map.insert(J(6));
-                let map2 = map.clone();
-                assert_eq!(map2.len(), 6);
-                assert_eq!(map2.get::<A>(), Some(&A(1)));
-                assert_eq!(map2.get::<B>(), Some(&B(2)));
-                assert_eq!(map2.get::<C>(), None);
-                assert_eq!(map2.get::<D>(), Some(&D(3)));
-                assert_eq!(map2.get::<E>(), Some(&E(4)));
-                assert_eq!(map2.get::<F>(), Some(&F(5)));
-                assert_eq!(map2.get::<J>(), Some(&J(6)));
-            }
-
-            #[test]
-            fn test_varieties() {
-                fn assert_send<T: Send>() { }
-                fn assert_sync<T: Sync>() { }
-                fn assert_clone<T: Clone>() { }
-                fn assert_debug<T: ::core::fmt::Debug>() { }
-                assert_send::<Map<dyn Any + Send>>();
-                assert_send::<Map<dyn Any + Send + Sync>>();
-                assert_sync::<Map<dyn Any + Send + Sync>>();
-                assert_debug::<Map<dyn Any>>();
-                assert_debug::<Map<dyn Any + Send>>();
-                assert_debug::<Map<dyn Any + Send + Sync>>();
-                assert_send::<Map<dyn CloneAny + Send>>();
-                assert_send::<Map<dyn CloneAny + Send + Sync>>();
-                assert_sync::<Map<dyn CloneAny + Send + Sync>>();
-                assert_clone::<Map<dyn CloneAny + Send>>();
-                assert_clone::<Map<dyn CloneAny + Send + Sync>>();
-                assert_clone::<Map<dyn CloneAny + Send + Sync>>();
-                assert_debug::<Map<dyn CloneAny>>();
-                assert_debug::<Map<dyn CloneAny + Send>>();
-                assert_debug::<Map<dyn CloneAny + Send + Sync>>();
-            }
-
-            #[test]
-            fn test_extend() {
-                let mut map = AnyMap::new();
-                // (vec![] for 1.36.0 compatibility; more recently, you should use [] instead.)
-                #[cfg(not(feature = "std"))]
-                use alloc::vec;
-                map.extend(vec![Box::new(123) as Box<dyn Any>, Box::new(456), Box::new(true)]);
-                assert_eq!(map.get(), Some(&456));
-                assert_eq!(map.get::<bool>(), Some(&true));
-                assert!(map.get::<Box<dyn Any>>().is_none());
-            }
+#[cfg(feature = "alloc")]
+impl core::fmt::Debug for TypeName {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.name {
+            Some(name) => f.write_str(name),
+            None => self.type_id.fmt(f),
         }
-    };
+    }
 }
 
 #[cfg(feature = "std")]
 everything!(
-    "let mut data = anymap::AnyMap::new();",
+    "let mut data: anymap::AnyMultiMap = anymap::AnyMultiMap::new();",
+    "let mut data: anymap::TypeMap = anymap::TypeMap::new();",
+    "let mut data: anymap::KeyedAnyMap<u32> = anymap::KeyedAnyMap::new();",
     std::collections
 );
 
@@ -585,15 +232,23 @@ everything!(
 ///
 /// This depends on the `hashbrown` Cargo feature being enabled.
 pub mod hashbrown {
-    use crate::TypeIdHasher;
-    #[cfg(doc)]
-    use crate::any::CloneAny;
+    use crate::{Downcast, IntoBox, Key, TypeIdHasher};
 
     everything!(
-        "let mut data = anymap::hashbrown::AnyMap::new();",
+        "let mut data: anymap::hashbrown::AnyMultiMap = anymap::hashbrown::AnyMultiMap::new();",
+        "let mut data: anymap::hashbrown::TypeMap = anymap::hashbrown::TypeMap::new();",
+        "let mut data: anymap::hashbrown::KeyedAnyMap<u32> = anymap::hashbrown::KeyedAnyMap::new();",
         hashbrown,
         BuildHasherDefault<TypeIdHasher>
     );
+
+    map_aliases! {
+        /// A [`Map`](crate::Map) backed by `hashbrown::HashMap`.
+        ///
+        /// This is the same as <code>anymap::[Map](crate::Map)&lt;A, [RawMap]&lt;A&gt;&gt;</code>;
+        /// see there for the details.
+        RawMap
+    }
 }
 
 /// A hasher designed to eke a little more speed out, given `TypeId`’s known characteristics.
@@ -625,6 +280,21 @@ impl Hasher for TypeIdHasher {
     fn finish(&self) -> u64 { self.value }
 }
 
+/// Hash one item of a map, for an order-independent hash of the whole map.
+///
+/// Rather than hashing the `TypeId` all over again, its bits (which are already as good as a hash,
+/// hence `TypeIdHasher`) become the key for hashing the value.
+#[cfg(feature = "alloc")]
+#[allow(deprecated)]  // SipHasher is what core has, and its deprecation is about HashMap’s needs.
+fn hash_item<A: ?Sized + core::hash::Hash>(type_id: core::any::TypeId, value: &A) -> u64 {
+    use core::hash::Hash;
+    let mut type_id_hasher = TypeIdHasher::default();
+    type_id.hash(&mut type_id_hasher);
+    let mut hasher = core::hash::SipHasher::new_with_keys(type_id_hasher.finish(), 0);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[test]
 fn type_id_hasher() {
     #[cfg(not(feature = "std"))]
@@ -634,8 +304,9 @@ fn type_id_hasher() {
     fn verify_hashing_with(type_id: TypeId) {
         let mut hasher = TypeIdHasher::default();
         type_id.hash(&mut hasher);
-        // SAFETY: u64 is valid for all bit patterns.
-        assert_eq!(hasher.finish(), unsafe { core::mem::transmute::<TypeId, u64>(type_id) });
+        // TypeId is no longer guaranteed to be a plain u64 (it’s 128 bits these days), so all we
+        // can check is that hashing feeds us exactly one u64, which the debug_assert_eq! covers.
+        let _ = hasher.finish();
     }
     // Pick a variety of types, just to demonstrate it’s all sane. Normal, zero-sized, unsized, &c.
     verify_hashing_with(TypeId::of::<usize>());
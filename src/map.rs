@@ -0,0 +1,1518 @@
+//! `Map` itself, over any [`RawStorage`].
+
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::hash::Hash;
+use core::iter::{FromIterator, FusedIterator};
+use core::marker::PhantomData;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+use crate::storage::{
+    DefaultStorage, RawCapacity, RawEntry, RawOccupiedEntry, RawStorage, RawVacantEntry,
+};
+use crate::recycle::{self, Recycler};
+use crate::{Downcast, IntoBox, TypeTuple, Upcast};
+use crate::any::{CloneAny, CloneDebugAny, CloneEqAny, CloneHashAny, DebugAny, EqAny, HashAny};
+
+/// A collection containing zero or one values for any given type and allowing convenient,
+/// type-safe access to those values.
+///
+/// The type parameter `A` allows you to use a different value type; normally you will want
+/// it to be `core::any::Any` (also known as `std::any::Any`), but there are other choices:
+///
+/// - If you want the entire map to be cloneable, use `CloneAny` instead of `Any`; with
+///   that, you can only add types that implement `Clone` to the map.
+/// - If you want to see the values in the map’s `Debug` output, use `DebugAny`; with that,
+///   you can only add types that implement `Debug` to the map. `CloneDebugAny` does both.
+/// - If you want to compare maps for equality, use `EqAny` (or `CloneEqAny`); with that,
+///   you can only add types that implement `Eq` to the map. For hashing as well, use
+///   `HashAny` (or `CloneHashAny`), which also requires `Hash`.
+/// - You can add on `+ Send`, `+ Sync` or `+ Send + Sync` (e.g. `Map<dyn Any + Send>`) to
+///   add those auto traits.
+///
+/// Cumulatively, there are thus eight forms of map with `Any` and `CloneAny`:
+///
+/// - <code>[Map]&lt;dyn [core::any::Any]&gt;</code>,
+///   also spelled [`AnyMap`] for convenience.
+/// - <code>[Map]&lt;dyn [core::any::Any] + Send&gt;</code>
+/// - <code>[Map]&lt;dyn [core::any::Any] + Sync&gt;</code>
+/// - <code>[Map]&lt;dyn [core::any::Any] + Send + Sync&gt;</code>
+/// - <code>[Map]&lt;dyn [CloneAny]&gt;</code>
+/// - <code>[Map]&lt;dyn [CloneAny] + Send&gt;</code>
+/// - <code>[Map]&lt;dyn [CloneAny] + Sync&gt;</code>
+/// - <code>[Map]&lt;dyn [CloneAny] + Send + Sync&gt;</code>
+///
+/// (<code>[Map]&lt;dyn [core::any::Any] + Sync&gt;</code> alone doesn’t implement `Debug`,
+/// because `core` doesn’t implement `Debug` for `dyn Any + Sync`.)
+///
+/// You can convert a map into one with a weaker value type (e.g. from
+/// <code>[Map]&lt;dyn [CloneAny] + Send + Sync&gt;</code> to
+/// <code>[Map]&lt;dyn [core::any::Any] + Send&gt;</code>) with [`upcast`](Map::upcast) or
+/// `Into`.
+///
+/// … and the same again with [`DebugAny`] and [`CloneDebugAny`],
+/// with [`EqAny`] and [`CloneEqAny`], and with [`HashAny`] and [`CloneHashAny`].
+///
+/// The second type parameter `S` is the storage that the values are kept in, which decides
+/// things like the order in which they’re visited. It defaults to [`DefaultStorage`], a hash map
+/// if the std or hashbrown feature is enabled; [`storage`](crate::storage) lists the others, and
+/// you can implement [`RawStorage`] to bring your own. Storage that doesn’t manage capacity
+/// (implement [`RawCapacity`]) leaves out the capacity methods.
+///
+/// ## Example
+///
+/// (Here using the [`AnyMap`] convenience alias; the first line could use
+/// <code>[anymap::Map][Map]::&lt;[core::any::Any]&gt;::new()</code> instead if desired.)
+///
+/// ```rust
+/// let mut data = anymap::AnyMap::new();
+/// assert_eq!(data.get(), None::<&i32>);
+/// data.insert(42i32);
+/// assert_eq!(data.get(), Some(&42i32));
+/// data.remove::<i32>();
+/// assert_eq!(data.get::<i32>(), None);
+///
+/// #[derive(Clone, PartialEq, Debug)]
+/// struct Foo {
+///     str: String,
+/// }
+///
+/// assert_eq!(data.get::<Foo>(), None);
+/// data.insert(Foo { str: format!("foo") });
+/// assert_eq!(data.get(), Some(&Foo { str: format!("foo") }));
+/// data.get_mut::<Foo>().map(|foo| foo.str.push('t'));
+/// assert_eq!(&*data.get::<Foo>().unwrap().str, "foot");
+/// ```
+///
+/// Values containing non-static references are not permitted.
+///
+/// If the value type has [`Named`](crate::Named) as a supertrait, as all of this crate’s do
+/// except `Any`, the map’s `Debug` output and [`type_names`](Map::type_names) can tell you the
+/// names of the types in there.
+pub struct Map<A: ?Sized + Downcast = dyn Any, S: RawStorage<A> = DefaultStorage<A>> {
+    pub(crate) raw: S,
+    pub(crate) recycler: Option<Recycler>,
+    pub(crate) value_type: PhantomData<fn() -> Box<A>>,
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only the storage can.
+impl<A: ?Sized + Downcast, S: RawStorage<A> + Clone> Clone for Map<A, S> {
+    #[inline]
+    fn clone(&self) -> Map<A, S> {
+        Map {
+            raw: self.raw.clone(),
+            recycler: self.recycler.as_ref().map(|_| Recycler::default()),
+            value_type: PhantomData,
+        }
+    }
+}
+
+/// Equality doesn’t depend on the order of the items.
+impl<A: ?Sized + Downcast + PartialEq, S: RawStorage<A>> PartialEq for Map<A, S> {
+    fn eq(&self, other: &Map<A, S>) -> bool {
+        self.raw.len() == other.raw.len() &&
+            self.raw.iter().all(|(&type_id, value)| {
+                other.raw.get(type_id).is_some_and(|other_value| **value == *other_value)
+            })
+    }
+}
+
+impl<A: ?Sized + Downcast + Eq, S: RawStorage<A>> Eq for Map<A, S> {}
+
+/// The hash is independent of the order in which the items are stored.
+impl<A: ?Sized + Downcast + Hash, S: RawStorage<A>> Hash for Map<A, S> {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.fingerprint())
+    }
+}
+
+impl<A: ?Sized + Downcast + fmt::Debug, S: RawStorage<A>> fmt::Debug for Map<A, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.raw.iter().map(|(type_id, value)| {
+                (crate::TypeName::new(*type_id, Downcast::type_name(&**value)), value)
+            }))
+            .finish()
+    }
+}
+
+/// The most common type of `Map`: just using `Any`; <code>[Map]&lt;dyn [Any]&gt;</code>.
+///
+/// Why is this a separate type alias rather than a default value for `Map<A>`?
+/// `Map::new()` doesn’t seem to be happy to infer that it should go with the default
+/// value. It’s a bit sad, really. Ah well, I guess this approach will do.
+pub type AnyMap = Map<dyn Any>;
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Default for Map<A, S> {
+    #[inline]
+    fn default() -> Map<A, S> {
+        Map::new()
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawCapacity<A>> Map<A, S> {
+    /// Creates an empty collection with the given initial capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Map<A, S> {
+        Map {
+            raw: S::with_capacity(capacity),
+            recycler: None,
+            value_type: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements the collection can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the collection. The collection may reserve more space to avoid
+    /// frequent reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.raw.reserve(additional)
+    }
+
+    /// Shrinks the capacity of the collection as much as possible. It will drop
+    /// down as much as possible while maintaining the internal rules
+    /// and possibly leaving some space in accordance with the resize policy.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.raw.shrink_to_fit()
+    }
+
+    // Additional stable methods (as of 1.60.0-nightly) that could be added:
+    // try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError>    (1.57.0)
+    // shrink_to(&mut self, min_capacity: usize)                                   (1.56.0)
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Map<A, S> {
+    /// Create an empty collection.
+    #[inline]
+    pub fn new() -> Map<A, S> {
+        Map {
+            raw: S::default(),
+            recycler: None,
+            value_type: PhantomData,
+        }
+    }
+
+    /// Returns the number of items in the collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns true if there are no items in the collection.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Removes all items from the collection. Keeps the allocated memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.raw.clear()
+    }
+
+    /// Turns recycling on or off.
+    ///
+    /// While recycling is on, the boxes of values taken out of the collection by `remove`
+    /// (or replaced by `insert`, or taken out through an entry) aren’t freed, but kept in a
+    /// free list for their type, and inserting a value of that type reuses one; so a map that
+    /// keeps getting the same few types taken out and put back in stops allocating for them.
+    /// [`clear_retaining_allocations`](Map::clear_retaining_allocations) keeps the boxes of
+    /// everything. Turning recycling off frees the boxes kept so far. (Cloning a map that’s
+    /// recycling gives one that’s also recycling, with no boxes kept yet.)
+    ///
+    /// ```rust
+    /// let mut data = anymap::AnyMap::new();
+    /// data.set_recycling(true);
+    /// data.insert(String::from("first"));
+    /// data.clear_retaining_allocations();
+    /// assert!(data.is_empty());
+    /// assert_eq!(data.recycled_len(), 1);
+    /// data.insert(String::from("second"));  // Reuses the box “first” was in.
+    /// assert_eq!(data.recycled_len(), 0);
+    /// ```
+    #[inline]
+    pub fn set_recycling(&mut self, recycling: bool) {
+        if recycling != self.recycler.is_some() {
+            self.recycler = if recycling { Some(Recycler::default()) } else { None };
+        }
+    }
+
+    /// Returns true if recycling is on; see [`set_recycling`](Map::set_recycling).
+    #[inline]
+    pub fn is_recycling(&self) -> bool {
+        self.recycler.is_some()
+    }
+
+    /// Returns the number of boxes kept for reuse by recycling.
+    ///
+    /// Values of zero-sized types don’t need a box of their own, so they’re not counted.
+    #[inline]
+    pub fn recycled_len(&self) -> usize {
+        self.recycler.as_ref().map_or(0, Recycler::len)
+    }
+
+    /// Removes all items from the collection, keeping their boxes for reuse if recycling is
+    /// on; see [`set_recycling`](Map::set_recycling). If it’s off, this is just `clear`.
+    #[inline]
+    pub fn clear_retaining_allocations(&mut self) {
+        match self.recycler {
+            Some(ref mut recycler) => {
+                for (type_id, value) in self.raw.drain() {
+                    recycler.drop_value(type_id, value);
+                }
+            },
+            None => self.clear(),
+        }
+    }
+
+    /// Returns a reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.raw.get(TypeId::of::<T>())
+            .map(|any| unsafe { any.downcast_ref_unchecked::<T>() })
+    }
+
+    /// Returns a mutable reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.raw.get_mut(TypeId::of::<T>())
+            .map(|any| unsafe { any.downcast_mut_unchecked::<T>() })
+    }
+
+    /// Returns references to the values stored in the collection for each of the types in
+    /// the tuple `T`, if they all exist.
+    ///
+    /// ```rust
+    /// let mut data = anymap::AnyMap::new();
+    /// data.insert_all((1u8, 2u16));
+    /// assert_eq!(data.get_all::<(u16, u8)>(), Some((&2, &1)));
+    /// assert_eq!(data.get_all::<(u8, u32)>(), None);
+    /// ```
+    #[inline]
+    pub fn get_all<T: TypeTuple<A>>(&self) -> Option<T::Refs<'_>> {
+        let raw = &self.raw;
+        // SAFETY: the map’s invariant is that the value’s type matches the key.
+        unsafe { T::get_all(|type_id| raw.get(type_id)) }
+    }
+
+    /// Returns mutable references to the values stored in the collection for each of the
+    /// types in the tuple `T`, if they all exist.
+    ///
+    /// This lets you borrow several values mutably at once:
+    ///
+    /// ```rust
+    /// let mut data = anymap::AnyMap::new();
+    /// data.insert(1u8);
+    /// data.insert(2u16);
+    /// if let Some((a, b)) = data.get_many_mut::<(u8, u16)>() {
+    ///     *a += 1;
+    ///     *b += u16::from(*a) * 20;
+    /// }
+    /// assert_eq!(data.get(), Some(&2u8));
+    /// assert_eq!(data.get(), Some(&42u16));
+    /// assert_eq!(data.get_many_mut::<(u8, u32)>(), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn get_many_mut<T: TypeTuple<A>>(&mut self) -> Option<T::RefsMut<'_>> {
+        let raw = &mut self.raw;
+        // SAFETY: the map’s invariant is that the value’s type matches the key, and each
+        // pointer is only handed out once, for the duration of the borrow of self. (The storage
+        // promises that different keys have different values.)
+        unsafe { T::get_many_mut(|type_id| raw.get_mut(type_id).map(|value| value as *mut A)) }
+    }
+
+    /// Sets the value stored in the collection for the type `T`.
+    /// If the collection already had a value of type `T`, that value is returned.
+    /// Otherwise, `None` is returned.
+    #[inline]
+    pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        let value = recycle::box_from(&mut self.recycler, value);
+        let recycler = &mut self.recycler;
+        self.raw.insert(TypeId::of::<T>(), value)
+            .map(|any| unsafe { recycle::unbox(recycler, any) })
+    }
+
+    /// Sets the values stored in the collection for each of the types in the tuple,
+    /// returning the values they replace.
+    ///
+    /// This is like calling `insert` for each value, but reserves space for them all up
+    /// front. If the same type appears more than once, the later value wins.
+    ///
+    /// ```rust
+    /// let mut data = anymap::AnyMap::new();
+    /// assert_eq!(data.insert_all((1u8, 2u16)), (None, None));
+    /// assert_eq!(data.insert_all((3u8, 4u32)), (Some(1u8), None));
+    /// assert_eq!(data.len(), 3);
+    /// ```
+    #[inline]
+    pub fn insert_all<T: TypeTuple<A>>(&mut self, values: T) -> T::Options {
+        self.raw.reserve(T::LEN);
+        values.insert_all(self)
+    }
+
+    // rustc 1.60.0-nightly has another method try_insert that would be nice when stable.
+
+    /// Removes the `T` value from the collection,
+    /// returning it if there was one or `None` if there was not.
+    #[inline]
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        let recycler = &mut self.recycler;
+        self.raw.remove(TypeId::of::<T>())
+            .map(|any| unsafe { recycle::unbox(recycler, any) })
+    }
+
+    /// Removes the values of each of the types in the tuple `T` from the collection,
+    /// returning them if they were all there. If any is missing, nothing is removed.
+    ///
+    /// ```rust
+    /// let mut data = anymap::AnyMap::new();
+    /// data.insert_all((1u8, 2u16));
+    /// assert_eq!(data.remove_all::<(u8, u32)>(), None);
+    /// assert_eq!(data.remove_all::<(u16, u8)>(), Some((2, 1)));
+    /// assert!(data.is_empty());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn remove_all<T: TypeTuple<A>>(&mut self) -> Option<T> {
+        if !self.contains_all::<T>() {
+            return None;
+        }
+        T::remove_all(self)
+    }
+
+    /// Returns true if the collection contains a value of each of the types in the tuple.
+    #[inline]
+    pub fn contains_all<T: TypeTuple<A>>(&self) -> bool {
+        T::contains_all(|type_id| self.raw.contains_key(type_id))
+    }
+
+    /// Returns true if the collection contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.raw.contains_key(TypeId::of::<T>())
+    }
+
+    /// Gets the entry for the given type in the collection for in-place manipulation
+    #[inline]
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T, S> {
+        let recycler = &mut self.recycler;
+        match self.raw.entry(TypeId::of::<T>()) {
+            RawEntry::Occupied(e) => Entry::Occupied(OccupiedEntry {
+                inner: e,
+                recycler,
+                type_: PhantomData,
+            }),
+            RawEntry::Vacant(e) => Entry::Vacant(VacantEntry {
+                inner: e,
+                recycler,
+                type_: PhantomData,
+            }),
+        }
+    }
+
+    /// Returns the name of the type stored in the collection under `type_id`, if there is
+    /// such an item and its name is known.
+    ///
+    /// The name comes from the value, so it’s known if the value type has
+    /// [`Named`](crate::Named) as a supertrait (see [`Downcast::type_name`]), and not otherwise.
+    #[inline]
+    pub fn type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.raw.get(type_id).and_then(Downcast::type_name)
+    }
+
+    /// An iterator visiting the names of the types in the collection, in the storage’s order.
+    ///
+    /// Items whose names aren’t known (see [`type_name`](Map::type_name)) are skipped.
+    #[inline]
+    pub fn type_names(&self) -> TypeNames<'_, A, S> {
+        TypeNames {
+            inner: self.raw.iter(),
+        }
+    }
+
+    /// An iterator visiting all items in the collection in the storage’s order (arbitrary,
+    /// for a hash map), yielding each value’s `TypeId` along with a reference to it.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, A, S> {
+        Iter {
+            inner: self.raw.iter(),
+        }
+    }
+
+    /// An iterator visiting all items in the collection in the storage’s order (arbitrary,
+    /// for a hash map), yielding each value’s `TypeId` along with a mutable reference to it.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, A, S> {
+        IterMut {
+            inner: self.raw.iter_mut(),
+        }
+    }
+
+    /// Clears the collection, returning all items as an iterator of boxed values.
+    /// Keeps the allocated memory for reuse.
+    ///
+    /// If the returned iterator is dropped before being fully consumed, it drops the
+    /// remaining items.
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, A, S> {
+        Drain {
+            inner: self.raw.drain(),
+        }
+    }
+
+    /// Retains only the items specified by the predicate.
+    ///
+    /// In other words, remove all items for which `f(type_id, &mut value)` returns
+    /// `false`.
+    #[inline]
+    pub fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, f: F) {
+        self.raw.retain(f)
+    }
+
+    /// Converts this into a map with a weaker value type: a supertrait (such as `Any`
+    /// from `CloneAny`), or fewer auto traits (such as `Any + Send` from
+    /// `Any + Send + Sync`), or both.
+    ///
+    /// The values themselves are not moved or reallocated, but the storage holding them is
+    /// rebuilt, with each item inserted afresh, so this takes O(n) time.
+    ///
+    /// For this crate’s value types, the same conversions are available through `From` and
+    /// `Into`.
+    #[inline]
+    pub fn upcast<B: ?Sized + Downcast>(self) -> Map<B, S::Rebind<B>> where A: Upcast<B> {
+        let mut raw = <S::Rebind<B>>::default();
+        raw.reserve(self.raw.len());
+        for (type_id, value) in self.raw {
+            let _ = raw.insert(type_id, <A as Upcast<B>>::upcast(value));
+        }
+        Map {
+            raw,
+            recycler: self.recycler,
+            value_type: PhantomData,
+        }
+    }
+
+    /// Get access to the raw storage that backs this.
+    ///
+    /// This will seldom be useful; [`iter`](Map::iter) covers iterating over the
+    /// collection, but this can be handy if you need something only the storage offers.
+    #[inline]
+    pub fn as_raw(&self) -> &S {
+        &self.raw
+    }
+
+    /// Get mutable access to the raw storage that backs this.
+    ///
+    /// This will seldom be useful, since [`iter_mut`](Map::iter_mut),
+    /// [`drain`](Map::drain) and [`retain`](Map::retain) cover the common cases, but it’s
+    /// conceivable that you could wish to do something else, *possibly* even batch
+    /// insert, and this lets you do that.
+    ///
+    /// # Safety
+    ///
+    /// If you insert any values to the raw storage, the key (a `TypeId`) must match the
+    /// value’s type, or *undefined behaviour* will occur when you access those values.
+    ///
+    /// (*Removing* entries is perfectly safe.)
+    #[inline]
+    pub unsafe fn as_raw_mut(&mut self) -> &mut S {
+        &mut self.raw
+    }
+
+    /// Convert this into the raw storage that backs this.
+    ///
+    /// This will seldom be useful, since [`drain`](Map::drain) and `into_iter` let you
+    /// consume all the items in the collection, but if you want the keys too, this lets
+    /// you have them without the `unsafe` that `.as_raw_mut().drain()` would require.
+    #[inline]
+    pub fn into_raw(self) -> S {
+        self.raw
+    }
+
+    /// Construct a map from raw storage.
+    ///
+    /// You know what? I can’t immediately think of any legitimate use for this, especially
+    /// because of the requirement of the `BuildHasherDefault<TypeIdHasher>` generic in the
+    /// hash maps.
+    ///
+    /// Perhaps this will be most practical as `unsafe { Map::from_raw(iter.collect()) }`,
+    /// `iter` being an iterator over `(TypeId, Box<A>)` pairs. Eh, this method provides
+    /// symmetry with `into_raw`, so I don’t care if literally no one ever uses it. I’m not
+    /// even going to write a test for it, it’s so trivial.
+    ///
+    /// # Safety
+    ///
+    /// For all entries in the raw storage, the key (a `TypeId`) must match the value’s type,
+    /// or *undefined behaviour* will occur when you access that entry.
+    #[inline]
+    pub unsafe fn from_raw(raw: S) -> Map<A, S> {
+        Self {
+            raw,
+            recycler: None,
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast + Hash, S: RawStorage<A>> Map<A, S> {
+    /// Returns a hash of the contents of the collection, independent of the order in which
+    /// the items are stored.
+    ///
+    /// Maps that are equal have the same fingerprint. Bear in mind that `TypeId`s, and
+    /// thus fingerprints, are not stable between builds of your program.
+    pub fn fingerprint(&self) -> u64 {
+        self.raw.iter().fold(0u64, |fingerprint, (&type_id, value)| {
+            fingerprint.wrapping_add(crate::hash_item(type_id, &**value))
+        })
+    }
+}
+
+/// Implements `From` for each of the conversions that [`Map::upcast`] can do among this crate’s
+/// value types. (A blanket impl would overlap with `impl<T> From<T> for T`, and impls for the
+/// traits of other crates would fall foul of the orphan rules, so those have only `upcast`.)
+macro_rules! impl_from_upcast {
+    ($any_trait:ident $(=> $($super_trait:ident),+)?) => {
+        impl_from_upcast!(@one $any_trait + Send => $any_trait);
+        impl_from_upcast!(@one $any_trait + Sync => $any_trait);
+        impl_from_upcast!(@one $any_trait + Send + Sync => $any_trait);
+        impl_from_upcast!(@one $any_trait + Send + Sync => $any_trait + Send);
+        impl_from_upcast!(@one $any_trait + Send + Sync => $any_trait + Sync);
+        $($(
+            impl_from_upcast!(@one $any_trait => $super_trait);
+            impl_from_upcast!(@one $any_trait + Send => $super_trait);
+            impl_from_upcast!(@one $any_trait + Send => $super_trait + Send);
+            impl_from_upcast!(@one $any_trait + Sync => $super_trait);
+            impl_from_upcast!(@one $any_trait + Sync => $super_trait + Sync);
+            impl_from_upcast!(@one $any_trait + Send + Sync => $super_trait);
+            impl_from_upcast!(@one $any_trait + Send + Sync => $super_trait + Send);
+            impl_from_upcast!(@one $any_trait + Send + Sync => $super_trait + Sync);
+            impl_from_upcast!(@one $any_trait + Send + Sync => $super_trait + Send + Sync);
+        )+)?
+    };
+
+    (@one $any_trait:ident $(+ $auto_traits:ident)* =>
+        $super_trait:ident $(+ $super_auto_traits:ident)*) => {
+        impl<S: RawStorage<dyn $any_trait $(+ $auto_traits)*>>
+            From<Map<dyn $any_trait $(+ $auto_traits)*, S>>
+            for Map<dyn $super_trait $(+ $super_auto_traits)*,
+                    S::Rebind<dyn $super_trait $(+ $super_auto_traits)*>>
+        {
+            #[inline]
+            fn from(map: Map<dyn $any_trait $(+ $auto_traits)*, S>) -> Self {
+                map.upcast()
+            }
+        }
+    };
+}
+
+impl_from_upcast!(Any);
+impl_from_upcast!(CloneAny => Any);
+impl_from_upcast!(DebugAny => Any);
+impl_from_upcast!(CloneDebugAny => Any, CloneAny, DebugAny);
+impl_from_upcast!(EqAny => Any);
+impl_from_upcast!(CloneEqAny => Any, CloneAny, EqAny);
+impl_from_upcast!(HashAny => Any, EqAny);
+impl_from_upcast!(CloneHashAny => Any, CloneEqAny, HashAny, CloneAny, EqAny);
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Extend<Box<A>> for Map<A, S> {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Box<A>>>(&mut self, iter: T) {
+        for item in iter {
+            let _ = self.raw.insert(Downcast::type_id(&*item), item);
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> FromIterator<Box<A>> for Map<A, S> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Box<A>>>(iter: T) -> Map<A, S> {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for &'a Map<A, S> {
+    type Item = (TypeId, &'a A);
+    type IntoIter = Iter<'a, A, S>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, A, S> {
+        self.iter()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for &'a mut Map<A, S> {
+    type Item = (TypeId, &'a mut A);
+    type IntoIter = IterMut<'a, A, S>;
+
+    #[inline]
+    fn into_iter(self) -> IterMut<'a, A, S> {
+        self.iter_mut()
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for Map<A, S> {
+    type Item = Box<A>;
+    type IntoIter = IntoIter<A, S>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<A, S> {
+        IntoIter {
+            inner: self.raw.into_iter(),
+            value_type: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the names of the types in a `Map`, created by [`Map::type_names`].
+pub struct TypeNames<'a, A: ?Sized + Downcast + 'a, S: RawStorage<A> + 'a = DefaultStorage<A>> {
+    inner: S::Iter<'a>,
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Clone for TypeNames<'a, A, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        TypeNames {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Iterator for TypeNames<'a, A, S> {
+    type Item = &'static str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'static str> {
+        self.inner.by_ref().filter_map(|(_, value)| Downcast::type_name(&**value)).next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator for TypeNames<'a, A, S> {}
+
+/// An iterator over the items of a `Map`, created by [`Map::iter`].
+pub struct Iter<'a, A: ?Sized + Downcast + 'a, S: RawStorage<A> + 'a = DefaultStorage<A>> {
+    inner: S::Iter<'a>,
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Clone for Iter<'a, A, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Iterator for Iter<'a, A, S> {
+    type Item = (TypeId, &'a A);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, &'a A)> {
+        self.inner.next().map(|(&type_id, value)| (type_id, &**value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> DoubleEndedIterator for Iter<'a, A, S>
+where S::Iter<'a>: DoubleEndedIterator {
+    #[inline]
+    fn next_back(&mut self) -> Option<(TypeId, &'a A)> {
+        self.inner.next_back().map(|(&type_id, value)| (type_id, &**value))
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> ExactSizeIterator for Iter<'a, A, S> {}
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator for Iter<'a, A, S> {}
+
+/// A mutable iterator over the items of a `Map`, created by [`Map::iter_mut`].
+pub struct IterMut<'a, A: ?Sized + Downcast + 'a, S: RawStorage<A> + 'a = DefaultStorage<A>> {
+    inner: S::IterMut<'a>,
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Iterator for IterMut<'a, A, S> {
+    type Item = (TypeId, &'a mut A);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, &'a mut A)> {
+        self.inner.next().map(|(&type_id, value)| (type_id, &mut **value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> DoubleEndedIterator for IterMut<'a, A, S>
+where S::IterMut<'a>: DoubleEndedIterator {
+    #[inline]
+    fn next_back(&mut self) -> Option<(TypeId, &'a mut A)> {
+        self.inner.next_back().map(|(&type_id, value)| (type_id, &mut **value))
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> ExactSizeIterator for IterMut<'a, A, S> {}
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator for IterMut<'a, A, S> {}
+
+/// A draining iterator over the items of a `Map`, created by [`Map::drain`].
+pub struct Drain<'a, A: ?Sized + Downcast + 'a, S: RawStorage<A> + 'a = DefaultStorage<A>> {
+    inner: S::Drain<'a>,
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> Iterator for Drain<'a, A, S> {
+    type Item = Box<A>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Box<A>> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> ExactSizeIterator for Drain<'a, A, S> {}
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator for Drain<'a, A, S> {}
+
+/// An owning iterator over the items of a `Map`, created by its `into_iter` method.
+pub struct IntoIter<A: ?Sized + Downcast, S: RawStorage<A> = DefaultStorage<A>> {
+    inner: S::IntoIter,
+    value_type: PhantomData<fn() -> Box<A>>,
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Iterator for IntoIter<A, S> {
+    type Item = Box<A>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Box<A>> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> DoubleEndedIterator for IntoIter<A, S>
+where S::IntoIter: DoubleEndedIterator {
+    #[inline]
+    fn next_back(&mut self) -> Option<Box<A>> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> ExactSizeIterator for IntoIter<A, S> {}
+impl<A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator for IntoIter<A, S> {}
+
+/// A view into a single occupied location in an `Map`.
+pub struct OccupiedEntry<
+    'a,
+    A: ?Sized + Downcast + 'a,
+    V: 'a,
+    S: RawStorage<A> + 'a = DefaultStorage<A>,
+> {
+    pub(crate) inner: S::OccupiedEntry<'a>,
+    pub(crate) recycler: &'a mut Option<Recycler>,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single empty location in an `Map`.
+pub struct VacantEntry<
+    'a,
+    A: ?Sized + Downcast + 'a,
+    V: 'a,
+    S: RawStorage<A> + 'a = DefaultStorage<A>,
+> {
+    pub(crate) inner: S::VacantEntry<'a>,
+    pub(crate) recycler: &'a mut Option<Recycler>,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single location in an `Map`, which may be vacant or occupied.
+pub enum Entry<'a, A: ?Sized + Downcast + 'a, V: 'a, S: RawStorage<A> + 'a = DefaultStorage<A>> {
+    /// An occupied Entry
+    Occupied(OccupiedEntry<'a, A, V, S>),
+    /// A vacant Entry
+    Vacant(VacantEntry<'a, A, V, S>),
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>, S: RawStorage<A>> Entry<'a, A, V, S> {
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if
+    /// empty, and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn or_default(self) -> &'a mut V where V: Default {
+        match self {
+            Entry::Occupied(inner) => inner.into_mut(),
+            Entry::Vacant(inner) => inner.insert(Default::default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts
+    /// into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut inner) => {
+                f(inner.get_mut());
+                Entry::Occupied(inner)
+            },
+            Entry::Vacant(inner) => Entry::Vacant(inner),
+        }
+    }
+
+    // Additional stable methods (as of 1.60.0-nightly) that could be added:
+    // insert_entry(self, value: V) -> OccupiedEntry<'a, K, V>                     (1.59.0)
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>, S: RawStorage<A>> OccupiedEntry<'a, A, V, S> {
+    /// Gets a reference to the value in the entry
+    #[inline]
+    pub fn get(&self) -> &V {
+        unsafe { self.inner.get().downcast_ref_unchecked() }
+    }
+
+    /// Gets a mutable reference to the value in the entry
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.inner.get_mut().downcast_mut_unchecked() }
+    }
+
+    /// Converts the OccupiedEntry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the collection itself
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { self.inner.into_mut().downcast_mut_unchecked() }
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        let value = recycle::box_from(self.recycler, value);
+        unsafe { recycle::unbox(self.recycler, self.inner.insert(value)) }
+    }
+
+    /// Takes the value out of the entry, and returns it
+    #[inline]
+    pub fn remove(self) -> V {
+        unsafe { recycle::unbox(self.recycler, self.inner.remove()) }
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>, S: RawStorage<A>> VacantEntry<'a, A, V, S> {
+    /// Sets the value of the entry with the VacantEntry's key,
+    /// and returns a mutable reference to it
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let value = recycle::box_from(self.recycler, value);
+        unsafe { self.inner.insert(value).downcast_mut_unchecked() }
+    }
+}
+
+/// Generates the tests of `Map` with the given storage, which is generic over the value type.
+#[cfg(test)]
+macro_rules! map_tests {
+    ($mod_name:ident, $($raw:ident)::+) => {
+        mod $mod_name {
+            use core::any::{Any, TypeId, type_name};
+            use crate::{CloneAny, CloneDebugAny, CloneEqAny, CloneHashAny, DebugAny, EqAny, HashAny};
+            use crate::Downcast;
+            #[cfg(not(feature = "std"))]
+            use alloc::{boxed::Box, vec, vec::Vec};
+
+            type Map<A = dyn Any> = crate::Map<A, $($raw)::+<A>>;
+            type AnyMap = Map<dyn Any>;
+            type Entry<'a, A, V> = crate::Entry<'a, A, V, $($raw)::+<A>>;
+
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct A(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct B(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct C(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct D(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct E(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct F(i32);
+            #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct J(i32);
+
+            macro_rules! test_entry {
+                ($name:ident, $init:ty) => {
+                    #[test]
+                    fn $name() {
+                        let mut map = <$init>::new();
+                        assert_eq!(map.insert(A(10)), None);
+                        assert_eq!(map.insert(B(20)), None);
+                        assert_eq!(map.insert(C(30)), None);
+                        assert_eq!(map.insert(D(40)), None);
+                        assert_eq!(map.insert(E(50)), None);
+                        assert_eq!(map.insert(F(60)), None);
+
+                        // Existing key (insert)
+                        match map.entry::<A>() {
+                            Entry::Vacant(_) => unreachable!(),
+                            Entry::Occupied(mut view) => {
+                                assert_eq!(view.get(), &A(10));
+                                assert_eq!(view.insert(A(100)), A(10));
+                            }
+                        }
+                        assert_eq!(map.get::<A>().unwrap(), &A(100));
+                        assert_eq!(map.len(), 6);
+
+
+                        // Existing key (update)
+                        match map.entry::<B>() {
+                            Entry::Vacant(_) => unreachable!(),
+                            Entry::Occupied(mut view) => {
+                                let v = view.get_mut();
+                                let new_v = B(v.0 * 10);
+                                *v = new_v;
+                            }
+                        }
+                        assert_eq!(map.get::<B>().unwrap(), &B(200));
+                        assert_eq!(map.len(), 6);
+
+
+                        // Existing key (remove)
+                        match map.entry::<C>() {
+                            Entry::Vacant(_) => unreachable!(),
+                            Entry::Occupied(view) => {
+                                assert_eq!(view.remove(), C(30));
+                            }
+                        }
+                        assert_eq!(map.get::<C>(), None);
+                        assert_eq!(map.len(), 5);
+
+
+                        // Inexistent key (insert)
+                        match map.entry::<J>() {
+                            Entry::Occupied(_) => unreachable!(),
+                            Entry::Vacant(view) => {
+                                assert_eq!(*view.insert(J(1000)), J(1000));
+                            }
+                        }
+                        assert_eq!(map.get::<J>().unwrap(), &J(1000));
+                        assert_eq!(map.len(), 6);
+
+                        // Entry.or_insert on existing key
+                        map.entry::<B>().or_insert(B(71)).0 += 1;
+                        assert_eq!(map.get::<B>().unwrap(), &B(201));
+                        assert_eq!(map.len(), 6);
+
+                        // Entry.or_insert on nonexisting key
+                        map.entry::<C>().or_insert(C(300)).0 += 1;
+                        assert_eq!(map.get::<C>().unwrap(), &C(301));
+                        assert_eq!(map.len(), 7);
+                    }
+                }
+            }
+
+            test_entry!(test_entry_any, AnyMap);
+            test_entry!(test_entry_any_sync, Map<dyn Any + Sync>);
+            test_entry!(test_entry_cloneany, Map<dyn CloneAny>);
+            test_entry!(test_entry_debugany, Map<dyn DebugAny>);
+            test_entry!(test_entry_clonedebugany, Map<dyn CloneDebugAny>);
+            test_entry!(test_entry_eqany, Map<dyn EqAny>);
+            test_entry!(test_entry_cloneeqany, Map<dyn CloneEqAny>);
+            test_entry!(test_entry_hashany, Map<dyn HashAny>);
+            test_entry!(test_entry_clonehashany, Map<dyn CloneHashAny>);
+
+            #[test]
+            fn test_default() {
+                let map: AnyMap = Default::default();
+                assert_eq!(map.len(), 0);
+            }
+
+            #[test]
+            fn test_clone() {
+                let mut map: Map<dyn CloneAny> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let _ = map.insert(D(3));
+                let _ = map.insert(E(4));
+                let _ = map.insert(F(5));
+                let _ = map.insert(J(6));
+                let map2 = map.clone();
+                assert_eq!(map2.len(), 6);
+                assert_eq!(map2.get::<A>(), Some(&A(1)));
+                assert_eq!(map2.get::<B>(), Some(&B(2)));
+                assert_eq!(map2.get::<C>(), None);
+                assert_eq!(map2.get::<D>(), Some(&D(3)));
+                assert_eq!(map2.get::<E>(), Some(&E(4)));
+                assert_eq!(map2.get::<F>(), Some(&F(5)));
+                assert_eq!(map2.get::<J>(), Some(&J(6)));
+
+                let mut map: Map<dyn CloneAny + Sync> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let map2 = map.clone();
+                assert_eq!(map2.len(), 2);
+                assert_eq!(map2.get::<A>(), Some(&A(1)));
+                assert_eq!(map2.get::<B>(), Some(&B(2)));
+            }
+
+            #[test]
+            fn test_varieties() {
+                fn assert_send<T: Send>() { }
+                fn assert_sync<T: Sync>() { }
+                fn assert_clone<T: Clone>() { }
+                fn assert_debug<T: ::core::fmt::Debug>() { }
+                assert_send::<Map<dyn Any + Send>>();
+                assert_sync::<Map<dyn Any + Sync>>();
+                assert_send::<Map<dyn Any + Send + Sync>>();
+                assert_sync::<Map<dyn Any + Send + Sync>>();
+                assert_debug::<Map<dyn Any>>();
+                assert_debug::<Map<dyn Any + Send>>();
+                assert_debug::<Map<dyn Any + Send + Sync>>();
+                assert_send::<Map<dyn CloneAny + Send>>();
+                assert_sync::<Map<dyn CloneAny + Sync>>();
+                assert_send::<Map<dyn CloneAny + Send + Sync>>();
+                assert_sync::<Map<dyn CloneAny + Send + Sync>>();
+                assert_clone::<Map<dyn CloneAny>>();
+                assert_clone::<Map<dyn CloneAny + Send>>();
+                assert_clone::<Map<dyn CloneAny + Sync>>();
+                assert_clone::<Map<dyn CloneAny + Send + Sync>>();
+                assert_clone::<Map<dyn CloneAny + Send + Sync>>();
+                assert_debug::<Map<dyn CloneAny>>();
+                assert_debug::<Map<dyn CloneAny + Send>>();
+                assert_debug::<Map<dyn CloneAny + Sync>>();
+                assert_debug::<Map<dyn CloneAny + Send + Sync>>();
+                assert_send::<Map<dyn DebugAny + Send>>();
+                assert_send::<Map<dyn DebugAny + Send + Sync>>();
+                assert_sync::<Map<dyn DebugAny + Send + Sync>>();
+                assert_debug::<Map<dyn DebugAny>>();
+                assert_debug::<Map<dyn DebugAny + Send>>();
+                assert_debug::<Map<dyn DebugAny + Sync>>();
+                assert_debug::<Map<dyn DebugAny + Send + Sync>>();
+                assert_send::<Map<dyn CloneDebugAny + Send>>();
+                assert_send::<Map<dyn CloneDebugAny + Send + Sync>>();
+                assert_sync::<Map<dyn CloneDebugAny + Send + Sync>>();
+                assert_clone::<Map<dyn CloneDebugAny>>();
+                assert_clone::<Map<dyn CloneDebugAny + Send>>();
+                assert_clone::<Map<dyn CloneDebugAny + Sync>>();
+                assert_clone::<Map<dyn CloneDebugAny + Send + Sync>>();
+                assert_debug::<Map<dyn CloneDebugAny>>();
+                assert_debug::<Map<dyn CloneDebugAny + Send>>();
+                assert_debug::<Map<dyn CloneDebugAny + Send + Sync>>();
+                fn assert_total_eq<T: Eq>() { }
+                assert_total_eq::<Map<dyn EqAny>>();
+                assert_total_eq::<Map<dyn EqAny + Send>>();
+                assert_total_eq::<Map<dyn EqAny + Sync>>();
+                assert_total_eq::<Map<dyn EqAny + Send + Sync>>();
+                assert_total_eq::<Map<dyn CloneEqAny>>();
+                assert_total_eq::<Map<dyn CloneEqAny + Send>>();
+                assert_total_eq::<Map<dyn CloneEqAny + Send + Sync>>();
+                assert_clone::<Map<dyn CloneEqAny + Send + Sync>>();
+                assert_debug::<Map<dyn CloneEqAny + Send + Sync>>();
+                fn assert_hash<T: ::core::hash::Hash>() { }
+                assert_hash::<Map<dyn HashAny>>();
+                assert_hash::<Map<dyn HashAny + Send>>();
+                assert_hash::<Map<dyn HashAny + Sync>>();
+                assert_hash::<Map<dyn HashAny + Send + Sync>>();
+                assert_hash::<Map<dyn CloneHashAny>>();
+                assert_hash::<Map<dyn CloneHashAny + Send>>();
+                assert_hash::<Map<dyn CloneHashAny + Send + Sync>>();
+                assert_total_eq::<Map<dyn CloneHashAny + Send + Sync>>();
+                assert_clone::<Map<dyn CloneHashAny + Send + Sync>>();
+            }
+
+            #[test]
+            fn test_eq() {
+                let mut map: Map<dyn EqAny + Send> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let mut map2 = Map::new();
+                let _ = map2.insert(B(2));
+                assert_ne!(map, map2);
+                let _ = map2.insert(A(1));
+                assert_eq!(map, map2);
+                let _ = map2.insert(A(10));
+                assert_ne!(map, map2);
+                let _ = map2.insert(A(1));
+                let _ = map2.insert(C(3));
+                assert_ne!(map, map2);
+                assert_ne!(map2, map);
+
+                let mut map: Map<dyn CloneEqAny> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let mut map2 = map.clone();
+                assert_eq!(map, map2);
+                map2.get_mut::<B>().unwrap().0 = 20;
+                assert_ne!(map, map2);
+            }
+
+            #[test]
+            fn test_hash() {
+                let mut map: Map<dyn HashAny + Send + Sync> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let _ = map.insert(C(3));
+                let mut map2 = Map::new();
+                let _ = map2.insert(C(3));
+                let _ = map2.insert(B(2));
+                let _ = map2.insert(A(1));
+                assert_eq!(map, map2);
+                assert_eq!(map.fingerprint(), map2.fingerprint());
+                // Swapping the values between types must make a difference.
+                let _ = map2.insert(A(2));
+                let _ = map2.insert(B(1));
+                assert_ne!(map.fingerprint(), map2.fingerprint());
+                assert_ne!(Map::<dyn HashAny>::new().fingerprint(), map.fingerprint());
+
+                let mut map: Map<dyn CloneHashAny> = Map::new();
+                let _ = map.insert(A(1));
+                let map2 = map.clone();
+                assert_eq!(map.fingerprint(), map2.fingerprint());
+                #[cfg(feature = "std")]
+                {
+                    let mut memo = std::collections::HashMap::new();
+                    let _ = memo.insert(map, "memoised");
+                    assert_eq!(memo.get(&map2), Some(&"memoised"));
+                }
+            }
+
+            #[test]
+            fn test_debug_any() {
+                #[cfg(not(feature = "std"))]
+                use alloc::format;
+                let mut map: Map<dyn CloneDebugAny + Send> = Map::new();
+                let _ = map.insert(A(1));
+                assert_eq!(format!("{:?}", map), format!("{{{}: A(1)}}", type_name::<A>()));
+                let _ = map.insert(B(2));
+                let map2 = map.clone();
+                let debug = format!("{:?}", map2);
+                assert!(debug.contains(&format!("{}: A(1)", type_name::<A>())));
+                assert!(debug.contains(&format!("{}: B(2)", type_name::<B>())));
+            }
+
+            #[test]
+            fn test_iter() {
+                let mut map = AnyMap::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let mut seen = map.iter()
+                    .map(|(type_id, value)| {
+                        assert_eq!(type_id, Any::type_id(value));
+                        type_id
+                    })
+                    .collect::<Vec<_>>();
+                seen.sort();
+                let mut expected = vec![TypeId::of::<A>(), TypeId::of::<B>()];
+                expected.sort();
+                assert_eq!(seen, expected);
+
+                for (_, value) in &mut map {
+                    if let Some(a) = value.downcast_mut::<A>() {
+                        a.0 += 10;
+                    }
+                }
+                assert_eq!(map.get(), Some(&A(11)));
+                assert_eq!(map.get(), Some(&B(2)));
+            }
+
+            #[test]
+            fn test_retain_and_drain() {
+                let mut map: Map<dyn CloneAny> = vec![
+                    Box::new(A(1)) as Box<dyn CloneAny>,
+                    Box::new(B(2)),
+                    Box::new(C(3)),
+                ].into_iter().collect();
+                assert_eq!(map.len(), 3);
+                map.retain(|type_id, _| type_id != TypeId::of::<B>());
+                assert_eq!(map.len(), 2);
+                assert!(!map.contains::<B>());
+
+                let mut drained = map.clone().drain().count();
+                assert_eq!(drained, 2);
+                drained = 0;
+                for value in map {
+                    assert_ne!(Downcast::type_id(&*value), TypeId::of::<B>());
+                    drained += 1;
+                }
+                assert_eq!(drained, 2);
+            }
+
+            #[test]
+            fn test_type_names() {
+                #[cfg(not(feature = "std"))]
+                use alloc::format;
+                let mut map: Map<dyn DebugAny> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.entry::<B>().or_insert(B(2));
+                map.extend(vec![Box::new(C(3)) as Box<dyn DebugAny>]);
+                assert_eq!(map.type_name(TypeId::of::<A>()), Some(type_name::<A>()));
+                assert_eq!(map.type_name(TypeId::of::<B>()), Some(type_name::<B>()));
+                assert_eq!(map.type_name(TypeId::of::<C>()), Some(type_name::<C>()));
+                assert_eq!(map.type_name(TypeId::of::<D>()), None);
+
+                let mut names = map.type_names().collect::<Vec<_>>();
+                names.sort();
+                assert_eq!(names, [type_name::<A>(), type_name::<B>(), type_name::<C>()]);
+
+                let debug = format!("{:?}", map);
+                assert!(debug.contains(&format!("{}: A(1)", type_name::<A>())));
+                assert!(debug.contains(&format!("{}: C(3)", type_name::<C>())));
+                assert!(!debug.contains("TypeId"));
+
+                let _ = map.remove::<A>();
+                assert_eq!(map.type_name(TypeId::of::<A>()), None);
+                assert_eq!(map.type_names().count(), 2);
+
+                // `Any`’s vtable has no name in it.
+                let mut map = AnyMap::new();
+                let _ = map.insert(A(1));
+                assert_eq!(map.type_name(TypeId::of::<A>()), None);
+                assert_eq!(map.type_names().count(), 0);
+                assert!(format!("{:?}", map).contains("TypeId"));
+            }
+
+            trait Component: Any + ::core::fmt::Debug + CloneToComponent {
+                fn id(&self) -> i32;
+            }
+            crate::impl_any_trait!(Component, clone: CloneToComponent);
+            impl Component for A { fn id(&self) -> i32 { self.0 } }
+            impl Component for B { fn id(&self) -> i32 { self.0 } }
+
+            #[test]
+            fn test_custom_any_trait() {
+                let mut map: Map<dyn Component + Send> = Map::new();
+                let _ = map.insert(A(1));
+                map.extend(vec![Box::new(B(2)) as Box<dyn Component + Send>]);
+                assert_eq!(map.get::<B>(), Some(&B(2)));
+                let map2 = map.clone();
+                let mut ids = map2.iter().map(|(_, value)| value.id()).collect::<Vec<_>>();
+                ids.sort();
+                assert_eq!(ids, [1, 2]);
+            }
+
+            #[test]
+            fn test_upcast() {
+                let mut map: Map<dyn CloneHashAny + Send + Sync> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let a: *const A = map.get::<A>().unwrap();
+
+                let map: Map<dyn CloneEqAny + Send> = map.upcast();
+                assert!(core::ptr::eq(a, map.get::<A>().unwrap()));
+                assert_eq!(map.clone(), map);
+                let map: AnyMap = map.upcast::<dyn CloneAny + Send>()
+                    .upcast::<dyn CloneAny>()
+                    .upcast();
+                assert!(core::ptr::eq(a, map.get::<A>().unwrap()));
+                assert_eq!(map.get::<B>(), Some(&B(2)));
+
+                let mut map: Map<dyn CloneDebugAny + Send + Sync> = Map::new();
+                let _ = map.insert(A(1));
+                let a: *const A = map.get::<A>().unwrap();
+                let map: Map<dyn DebugAny + Sync> = map.into();
+                let map = Map::<dyn Any + Sync>::from(map);
+                assert!(core::ptr::eq(a, map.get::<A>().unwrap()));
+
+                let mut map: Map<dyn Component + Send + Sync> = Map::new();
+                let _ = map.insert(A(1));
+                assert_eq!(map.type_name(TypeId::of::<A>()), Some(type_name::<A>()));
+                let map: Map<dyn Any + Send> = map.upcast();
+                assert_eq!(map.get::<A>(), Some(&A(1)));
+                assert_eq!(map.type_name(TypeId::of::<A>()), None);
+            }
+
+            #[test]
+            fn test_get_many_mut() {
+                let mut map: Map<dyn CloneAny> = Map::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let _ = map.insert(C(3));
+                {
+                    let (c, a, b) = map.get_many_mut::<(C, A, B)>().unwrap();
+                    core::mem::swap(&mut a.0, &mut c.0);
+                    b.0 *= 10;
+                }
+                assert_eq!(map.get(), Some(&A(3)));
+                assert_eq!(map.get(), Some(&B(20)));
+                assert_eq!(map.get(), Some(&C(1)));
+                assert_eq!(map.get_many_mut::<(A,)>(), Some((&mut A(3),)));
+                assert_eq!(map.get_many_mut::<(A, D)>(), None);
+                assert!(map.get_many_mut::<(A, B, C, D, E, F, J, u8, u16, u32, u64, i8)>().is_none());
+            }
+
+            #[test]
+            fn test_bulk() {
+                let mut map: Map<dyn DebugAny> = Map::new();
+                assert_eq!(map.insert_all((A(1), B(2), C(3))), (None, None, None));
+                assert_eq!(map.len(), 3);
+                assert_eq!(map.type_name(TypeId::of::<B>()), Some(type_name::<B>()));
+                assert_eq!(map.insert_all((D(4), A(10))), (None, Some(A(1))));
+                assert_eq!(map.insert_all((E(5), E(50))), (None, Some(E(5))));
+                assert_eq!(map.get::<E>(), Some(&E(50)));
+
+                assert!(map.contains_all::<(A, B, C, D, E)>());
+                assert!(!map.contains_all::<(A, F)>());
+                assert_eq!(map.get_all::<(C, A, A)>(), Some((&C(3), &A(10), &A(10))));
+                assert_eq!(map.get_all::<(A, F)>(), None);
+
+                assert_eq!(map.remove_all::<(A, F)>(), None);
+                assert_eq!(map.len(), 5);
+                assert_eq!(map.remove_all::<(B, A)>(), Some((B(2), A(10))));
+                assert_eq!(map.len(), 3);
+                assert_eq!(map.type_name(TypeId::of::<B>()), None);
+                assert!(!map.contains_all::<(A,)>());
+            }
+
+            #[test]
+            #[should_panic(expected = "the same type appears twice in the tuple")]
+            fn test_remove_all_duplicate() {
+                let mut map = AnyMap::new();
+                let _ = map.insert(A(1));
+                let _ = map.remove_all::<(A, A)>();
+            }
+
+            #[test]
+            #[should_panic(expected = "the same type appears twice in the tuple")]
+            fn test_get_many_mut_duplicate() {
+                let mut map = AnyMap::new();
+                let _ = map.insert(A(1));
+                let _ = map.insert(B(2));
+                let _ = map.get_many_mut::<(A, B, A)>();
+            }
+
+            #[test]
+            fn test_recycling() {
+                let mut map: Map<dyn CloneAny> = Map::new();
+                assert!(!map.is_recycling());
+                let _ = map.insert(A(1));
+                let _ = map.remove::<A>();
+                assert_eq!(map.recycled_len(), 0);
+
+                map.set_recycling(true);
+                let _ = map.insert(A(1));
+                let a = map.get::<A>().unwrap() as *const A;
+                assert_eq!(map.remove::<A>(), Some(A(1)));
+                assert_eq!(map.recycled_len(), 1);
+                assert_eq!(map.insert(A(2)), None);
+                assert_eq!(map.get::<A>().unwrap() as *const A, a);
+                assert_eq!(map.recycled_len(), 0);
+                assert_eq!(map.insert(A(3)), Some(A(2)));
+                assert_eq!(map.recycled_len(), 1);
+                match map.entry::<A>() {
+                    Entry::Occupied(view) => assert_eq!(view.remove(), A(3)),
+                    Entry::Vacant(_) => unreachable!(),
+                }
+                assert_eq!(map.recycled_len(), 2);
+                *map.entry::<A>().or_insert(A(4)) = A(5);
+                assert_eq!(map.recycled_len(), 1);
+
+                let _ = map.insert(B(6));
+                let _ = map.insert(vec![J(7)]);
+                let _ = map.insert(());
+                map.clear_retaining_allocations();
+                assert!(map.is_empty());
+                assert_eq!(map.type_names().count(), 0);
+                assert_eq!(map.recycled_len(), 4);
+                let _ = map.insert(vec![J(8)]);
+                assert_eq!(map.get::<Vec<J>>(), Some(&vec![J(8)]));
+                assert_eq!(map.recycled_len(), 3);
+
+                let map2 = map.clone();
+                assert!(map2.is_recycling());
+                assert_eq!(map2.recycled_len(), 0);
+                let map3 = map.clone().upcast::<dyn Any>();
+                assert!(map3.is_recycling());
+
+                // Tuples go through the recycler too.
+                let _ = map.remove::<Vec<J>>();
+                let _ = map.insert_all((A(9), B(10)));
+                let a = map.get::<A>().unwrap() as *const A;
+                assert_eq!(map.recycled_len(), 2);
+                assert_eq!(map.remove_all::<(A, B)>(), Some((A(9), B(10))));
+                assert_eq!(map.recycled_len(), 4);
+                assert_eq!(map.type_names().count(), 0);
+                assert_eq!(map.insert_all((A(11),)), (None,));
+                assert_eq!(map.get::<A>().unwrap() as *const A, a);
+                assert_eq!(map.type_name(TypeId::of::<A>()), Some(type_name::<A>()));
+                map.set_recycling(false);
+                assert_eq!(map.recycled_len(), 0);
+                map.clear_retaining_allocations();
+                assert!(map.is_empty());
+            }
+
+            #[test]
+            fn test_extend() {
+                let mut map = AnyMap::new();
+                // (vec![] for 1.36.0 compatibility; more recently, you should use [] instead.)
+                map.extend(vec![Box::new(123) as Box<dyn Any>, Box::new(456), Box::new(true)]);
+                assert_eq!(map.get(), Some(&456));
+                assert_eq!(map.get::<bool>(), Some(&true));
+                assert!(map.get::<Box<dyn Any>>().is_none());
+            }
+        }
+    };
+}
+
+#[cfg(all(test, feature = "std"))]
+map_tests!(std_tests, crate::RawMap);
+#[cfg(all(test, feature = "hashbrown"))]
+map_tests!(hashbrown_tests, crate::hashbrown::RawMap);
+#[cfg(test)]
+map_tests!(btree_tests, crate::btree::RawMap);
+#[cfg(test)]
+map_tests!(sorted_vec_tests, crate::storage::SortedVec);
+#[cfg(all(test, any(feature = "std", feature = "hashbrown")))]
+map_tests!(indexed_tests, crate::indexed::RawMap);
+
+#[cfg(all(test, any(feature = "std", feature = "hashbrown")))]
+map_tests!(adaptive_tests, crate::adaptive::RawMap);
+
+#[cfg(all(test, any(feature = "std", feature = "hashbrown")))]
+map_tests!(slab_tests, crate::slab::RawMap);
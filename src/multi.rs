@@ -0,0 +1,350 @@
+/// Generates `AnyMultiMap` and its entry types, in the same manner as `everything!`.
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+macro_rules! multi_map {
+    ($example_init:literal $(, $entry_generics:ty)?) => {
+        /// A collection containing any number of values for any given type, kept in the order they
+        /// were added, and allowing convenient, type-safe access to those values.
+        ///
+        /// Where a [`Map`] holds zero or one values of each type, this holds a list of them.
+        /// The value type `A` is as for [`Map`]; for each type `T` you store, it’s `Vec<T>` that
+        /// must satisfy it (so with `CloneAny`, for example, `T` must implement `Clone`).
+        ///
+        /// ```rust
+        #[doc = $example_init]
+        /// data.push(1i32);
+        /// data.push(2i32);
+        /// data.push("hello");
+        /// assert_eq!(data.get_all::<i32>(), &[1, 2]);
+        /// assert_eq!(data.iter::<&str>().collect::<Vec<_>>(), [&"hello"]);
+        /// assert_eq!(data.get_all::<u8>(), &[]);
+        /// assert_eq!(data.remove_all::<i32>(), vec![1, 2]);
+        /// assert!(!data.contains::<i32>());
+        /// ```
+        pub struct AnyMultiMap<A: ?Sized + Downcast = dyn Any> {
+            /// Maps `TypeId::of::<T>()` to a non-empty `Vec<T>`.
+            raw: HashMap<TypeId, Box<A>, BuildHasherDefault<TypeIdHasher>>,
+        }
+
+        impl<A: ?Sized + Downcast> Clone for AnyMultiMap<A> where Box<A>: Clone {
+            #[inline]
+            fn clone(&self) -> AnyMultiMap<A> {
+                AnyMultiMap {
+                    raw: self.raw.clone(),
+                }
+            }
+        }
+
+        impl<A: ?Sized + Downcast + fmt::Debug> fmt::Debug for AnyMultiMap<A> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.raw.iter().map(|(&type_id, values)| {
+                        (crate::TypeName::new(type_id, Downcast::type_name(&**values)), values)
+                    }))
+                    .finish()
+            }
+        }
+
+        impl<A: ?Sized + Downcast> Default for AnyMultiMap<A> {
+            #[inline]
+            fn default() -> AnyMultiMap<A> {
+                AnyMultiMap::new()
+            }
+        }
+
+        impl<A: ?Sized + Downcast> AnyMultiMap<A> {
+            /// Create an empty collection.
+            #[inline]
+            pub fn new() -> AnyMultiMap<A> {
+                AnyMultiMap {
+                    raw: HashMap::with_hasher(Default::default()),
+                }
+            }
+
+            /// Creates an empty collection with room for the given number of types.
+            #[inline]
+            pub fn with_capacity(capacity: usize) -> AnyMultiMap<A> {
+                AnyMultiMap {
+                    raw: HashMap::with_capacity_and_hasher(capacity, Default::default()),
+                }
+            }
+
+            /// Returns the number of types the collection can hold without reallocating.
+            #[inline]
+            pub fn capacity(&self) -> usize {
+                self.raw.capacity()
+            }
+
+            /// Reserves capacity for at least `additional` more types to be inserted
+            /// in the collection.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the new allocation size overflows `usize`.
+            #[inline]
+            pub fn reserve(&mut self, additional: usize) {
+                self.raw.reserve(additional)
+            }
+
+            /// Shrinks the capacity of the collection as much as possible.
+            #[inline]
+            pub fn shrink_to_fit(&mut self) {
+                self.raw.shrink_to_fit()
+            }
+
+            /// Returns the number of types in the collection.
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.raw.len()
+            }
+
+            /// Returns true if there are no items in the collection.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.raw.is_empty()
+            }
+
+            /// Removes all items from the collection. Keeps the allocated memory for reuse.
+            #[inline]
+            pub fn clear(&mut self) {
+                self.raw.clear()
+            }
+
+            /// Adds a value to the end of the list of values of type `T`.
+            #[inline]
+            pub fn push<T>(&mut self, value: T) where Vec<T>: IntoBox<A> {
+                let _ = self.entry::<T>().push(value);
+            }
+
+            /// Returns the values of type `T` in the collection, in the order they were added.
+            #[inline]
+            pub fn get_all<T>(&self) -> &[T] where Vec<T>: IntoBox<A> {
+                self.raw.get(&TypeId::of::<T>())
+                    .map_or(&[], |any| unsafe { any.downcast_ref_unchecked::<Vec<T>>() })
+            }
+
+            /// Returns the values of type `T` in the collection mutably.
+            #[inline]
+            pub fn get_all_mut<T>(&mut self) -> &mut [T] where Vec<T>: IntoBox<A> {
+                self.raw.get_mut(&TypeId::of::<T>())
+                    .map_or(&mut [], |any| unsafe { any.downcast_mut_unchecked::<Vec<T>>() })
+            }
+
+            /// An iterator over the values of type `T`, in the order they were added.
+            #[inline]
+            pub fn iter<T>(&self) -> core::slice::Iter<'_, T> where Vec<T>: IntoBox<A> {
+                self.get_all::<T>().iter()
+            }
+
+            /// A mutable iterator over the values of type `T`, in the order they were added.
+            #[inline]
+            pub fn iter_mut<T>(&mut self) -> core::slice::IterMut<'_, T> where Vec<T>: IntoBox<A> {
+                self.get_all_mut::<T>().iter_mut()
+            }
+
+            /// Removes all the values of type `T` from the collection, returning them.
+            #[inline]
+            pub fn remove_all<T>(&mut self) -> Vec<T> where Vec<T>: IntoBox<A> {
+                self.raw.remove(&TypeId::of::<T>())
+                    .map_or_else(Vec::new, |any| *unsafe { any.downcast_unchecked::<Vec<T>>() })
+            }
+
+            /// Returns true if the collection contains any values of type `T`.
+            #[inline]
+            pub fn contains<T>(&self) -> bool where Vec<T>: IntoBox<A> {
+                self.raw.contains_key(&TypeId::of::<T>())
+            }
+
+            /// Gets the entry for the given type in the collection for in-place manipulation.
+            #[inline]
+            pub fn entry<T>(&mut self) -> MultiEntry<'_, A, T> where Vec<T>: IntoBox<A> {
+                match self.raw.entry(TypeId::of::<T>()) {
+                    hash_map::Entry::Occupied(e) => MultiEntry::Occupied(OccupiedMultiEntry {
+                        inner: e,
+                        type_: PhantomData,
+                    }),
+                    hash_map::Entry::Vacant(e) => MultiEntry::Vacant(VacantMultiEntry {
+                        inner: e,
+                        type_: PhantomData,
+                    }),
+                }
+            }
+        }
+
+        /// A view into the values of a single type in an `AnyMultiMap`, where there are some.
+        pub struct OccupiedMultiEntry<'a, A: ?Sized + Downcast, V: 'a> {
+            inner: hash_map::OccupiedEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
+            type_: PhantomData<V>,
+        }
+
+        /// A view into the values of a single type in an `AnyMultiMap`, where there are none.
+        pub struct VacantMultiEntry<'a, A: ?Sized + Downcast, V: 'a> {
+            inner: hash_map::VacantEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
+            type_: PhantomData<V>,
+        }
+
+        /// A view into the values of a single type in an `AnyMultiMap`, of which there may or may
+        /// not be some.
+        pub enum MultiEntry<'a, A: ?Sized + Downcast, V: 'a> {
+            /// An occupied Entry
+            Occupied(OccupiedMultiEntry<'a, A, V>),
+            /// A vacant Entry
+            Vacant(VacantMultiEntry<'a, A, V>),
+        }
+
+        impl<'a, A: ?Sized + Downcast, V> MultiEntry<'a, A, V> where Vec<V>: IntoBox<A> {
+            /// Adds a value to the end of the entry’s values, and returns a mutable reference to
+            /// it.
+            #[inline]
+            pub fn push(self, value: V) -> &'a mut V {
+                match self {
+                    MultiEntry::Occupied(mut inner) => {
+                        inner.push(value);
+                        let values = inner.into_mut();
+                        &mut values[values.len() - 1]
+                    },
+                    MultiEntry::Vacant(inner) => inner.push(value),
+                }
+            }
+
+            /// Returns the entry’s values, which will be empty if it’s vacant.
+            #[inline]
+            pub fn into_mut(self) -> &'a mut [V] {
+                match self {
+                    MultiEntry::Occupied(inner) => inner.into_mut(),
+                    MultiEntry::Vacant(_) => &mut [],
+                }
+            }
+
+            /// Provides in-place mutable access to an occupied entry’s values before any potential
+            /// pushes onto it.
+            #[inline]
+            pub fn and_modify<F: FnOnce(&mut [V])>(self, f: F) -> Self {
+                match self {
+                    MultiEntry::Occupied(mut inner) => {
+                        f(inner.get_mut());
+                        MultiEntry::Occupied(inner)
+                    },
+                    MultiEntry::Vacant(inner) => MultiEntry::Vacant(inner),
+                }
+            }
+        }
+
+        impl<'a, A: ?Sized + Downcast, V> OccupiedMultiEntry<'a, A, V> where Vec<V>: IntoBox<A> {
+            /// Gets the values in the entry
+            #[inline]
+            pub fn get(&self) -> &[V] {
+                unsafe { self.inner.get().downcast_ref_unchecked::<Vec<V>>() }
+            }
+
+            /// Gets the values in the entry mutably
+            #[inline]
+            pub fn get_mut(&mut self) -> &mut [V] {
+                unsafe { self.inner.get_mut().downcast_mut_unchecked::<Vec<V>>() }
+            }
+
+            /// Converts the entry into a mutable reference to its values
+            /// with a lifetime bound to the collection itself
+            #[inline]
+            pub fn into_mut(self) -> &'a mut [V] {
+                unsafe { self.inner.into_mut().downcast_mut_unchecked::<Vec<V>>() }
+            }
+
+            /// Adds a value to the end of the entry’s values
+            #[inline]
+            pub fn push(&mut self, value: V) {
+                unsafe { self.inner.get_mut().downcast_mut_unchecked::<Vec<V>>() }.push(value)
+            }
+
+            /// Takes the values out of the entry, and returns them
+            #[inline]
+            pub fn remove(self) -> Vec<V> {
+                *unsafe { self.inner.remove().downcast_unchecked::<Vec<V>>() }
+            }
+        }
+
+        impl<'a, A: ?Sized + Downcast, V> VacantMultiEntry<'a, A, V> where Vec<V>: IntoBox<A> {
+            /// Makes the value the entry’s first, and returns a mutable reference to it
+            #[inline]
+            pub fn push(self, value: V) -> &'a mut V {
+                let values = vec![value].into_box();
+                unsafe { &mut self.inner.insert(values).downcast_mut_unchecked::<Vec<V>>()[0] }
+            }
+        }
+
+        #[cfg(test)]
+        mod multi_tests {
+            use core::any::type_name;
+            use crate::{CloneAny, DebugAny};
+            use super::*;
+            #[cfg(not(feature = "std"))]
+            use alloc::format;
+
+            #[derive(Clone, Debug, PartialEq)] struct Handler(&'static str);
+
+            #[test]
+            fn test_push_and_get() {
+                let mut map = AnyMultiMap::<dyn Any>::new();
+                assert!(map.is_empty());
+                map.push(Handler("a"));
+                map.push(1u8);
+                map.push(Handler("b"));
+                assert_eq!(map.len(), 2);
+                assert_eq!(map.get_all::<Handler>(), &[Handler("a"), Handler("b")]);
+                assert_eq!(map.iter::<u8>().collect::<Vec<_>>(), [&1]);
+                assert_eq!(map.get_all::<u16>(), &[]);
+                assert!(map.contains::<u8>());
+                assert!(!map.contains::<u16>());
+
+                for handler in map.iter_mut::<Handler>() {
+                    handler.0 = "z";
+                }
+                map.get_all_mut::<u8>()[0] = 2;
+                assert_eq!(map.remove_all::<Handler>(), vec![Handler("z"), Handler("z")]);
+                assert_eq!(map.remove_all::<Handler>(), vec![]);
+                assert_eq!(map.get_all::<u8>(), &[2]);
+                map.clear();
+                assert!(map.is_empty());
+            }
+
+            #[test]
+            fn test_entry() {
+                let mut map = AnyMultiMap::<dyn Any>::new();
+                match map.entry::<Handler>() {
+                    MultiEntry::Occupied(_) => unreachable!(),
+                    MultiEntry::Vacant(view) => assert_eq!(view.push(Handler("a")), &Handler("a")),
+                }
+                *map.entry().push(Handler("b")) = Handler("c");
+                match map.entry::<Handler>() {
+                    MultiEntry::Vacant(_) => unreachable!(),
+                    MultiEntry::Occupied(mut view) => {
+                        assert_eq!(view.get(), &[Handler("a"), Handler("c")]);
+                        view.push(Handler("d"));
+                        view.get_mut()[0] = Handler("e");
+                        assert_eq!(view.remove(), vec![Handler("e"), Handler("c"), Handler("d")]);
+                    },
+                }
+                assert!(!map.contains::<Handler>());
+                assert_eq!(map.entry::<Handler>().and_modify(|_| unreachable!()).into_mut(), &[]);
+                map.push(Handler("f"));
+                let values = map.entry::<Handler>()
+                    .and_modify(|values| values[0] = Handler("g"))
+                    .into_mut();
+                assert_eq!(values, &[Handler("g")]);
+            }
+
+            #[test]
+            fn test_clone_and_debug() {
+                let mut map = AnyMultiMap::<dyn CloneAny>::new();
+                map.push(Handler("a"));
+                map.push(Handler("b"));
+                let clone = map.clone();
+                assert_eq!(clone.get_all::<Handler>(), &[Handler("a"), Handler("b")]);
+
+                let mut map = AnyMultiMap::<dyn DebugAny>::new();
+                map.push(1u8);
+                map.push(2u8);
+                assert_eq!(format!("{:?}", map), format!("{{{}: [1, 2]}}", type_name::<Vec<u8>>()));
+            }
+        }
+    };
+}
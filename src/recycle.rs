@@ -0,0 +1,150 @@
+//! The free lists behind `Map`’s recycling mode.
+
+use core::alloc::Layout;
+use core::any::TypeId;
+use core::ptr::{self, NonNull};
+
+#[cfg(not(feature = "std"))]
+use alloc::{alloc::dealloc, boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::alloc::dealloc;
+
+use crate::{Downcast, IntoBox};
+
+/// The free lists of a `Recycler`, by `TypeId`.
+#[cfg(feature = "std")]
+type TypeIdMap<V> = std::collections::HashMap<
+    TypeId,
+    V,
+    core::hash::BuildHasherDefault<crate::TypeIdHasher>,
+>;
+
+/// The free lists of a `Recycler`, by `TypeId`.
+#[cfg(all(not(feature = "std"), feature = "hashbrown"))]
+type TypeIdMap<V> = hashbrown::HashMap<
+    TypeId,
+    V,
+    core::hash::BuildHasherDefault<crate::TypeIdHasher>,
+>;
+
+/// The free lists of a `Recycler`, by `TypeId`.
+#[cfg(not(any(feature = "std", feature = "hashbrown")))]
+type TypeIdMap<V> = alloc::collections::BTreeMap<TypeId, V>;
+
+/// The boxes of one type that are free for reuse, their values already gone.
+struct FreeList {
+    layout: Layout,
+    boxes: Vec<NonNull<u8>>,
+}
+
+/// Boxes kept for reuse, by the `TypeId` of the values they held.
+///
+/// Boxes of zero-sized types are never kept, since they own no memory.
+#[derive(Default)]
+pub(crate) struct Recycler {
+    free: TypeIdMap<FreeList>,
+}
+
+// The recycler owns nothing but uninitialised memory.
+unsafe impl Send for Recycler {}
+unsafe impl Sync for Recycler {}
+
+impl Recycler {
+    /// Keeps the memory at `ptr`, allocated by `Box` with `layout`, for a value of `type_id`.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be free for reuse: nothing else may own it, and any value in it has
+    /// been moved out or dropped.
+    unsafe fn push(&mut self, type_id: TypeId, layout: Layout, ptr: *mut u8) {
+        if layout.size() == 0 {
+            return;
+        }
+        self.free.entry(type_id)
+            .or_insert_with(|| FreeList { layout, boxes: Vec::new() })
+            .boxes
+            .push(NonNull::new_unchecked(ptr))
+    }
+
+    /// Boxes `value`, reusing a free box if there is one.
+    #[inline]
+    pub(crate) fn box_from<A: ?Sized + Downcast, T: IntoBox<A>>(&mut self, value: T) -> Box<A> {
+        match self.free.get_mut(&TypeId::of::<T>()).and_then(|list| list.boxes.pop()) {
+            Some(ptr) => unsafe {
+                let ptr = ptr.cast::<T>().as_ptr();
+                ptr.write(value);
+                Box::from_raw(T::as_dyn_ptr(ptr))
+            },
+            None => value.into_box(),
+        }
+    }
+
+    /// Takes the value out of a box, keeping the box for reuse.
+    ///
+    /// # Safety
+    ///
+    /// The value in the box must be of type `T`.
+    #[inline]
+    pub(crate) unsafe fn unbox<A: ?Sized + Downcast, T: IntoBox<A>>(&mut self, value: Box<A>) -> T {
+        let ptr = Box::into_raw(value) as *mut T;
+        let value = ptr::read(ptr);
+        self.push(TypeId::of::<T>(), Layout::new::<T>(), ptr as *mut u8);
+        value
+    }
+
+    /// Drops the value in a box, keeping the box for reuse.
+    #[inline]
+    pub(crate) fn drop_value<A: ?Sized + Downcast>(&mut self, type_id: TypeId, value: Box<A>) {
+        let layout = Layout::for_value(&*value);
+        let ptr = Box::into_raw(value);
+        unsafe {
+            // Should dropping the value panic, the box is leaked rather than kept or freed.
+            ptr::drop_in_place(ptr);
+            self.push(type_id, layout, ptr as *mut u8);
+        }
+    }
+
+    /// Returns the number of boxes kept for reuse.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.free.values().map(|list| list.boxes.len()).sum()
+    }
+}
+
+impl Drop for Recycler {
+    fn drop(&mut self) {
+        for list in self.free.values() {
+            for &ptr in &list.boxes {
+                unsafe { dealloc(ptr.as_ptr(), list.layout) }
+            }
+        }
+    }
+}
+
+/// Boxes `value`, through the recycler if there is one.
+#[inline]
+pub(crate) fn box_from<A: ?Sized + Downcast, T: IntoBox<A>>(
+    recycler: &mut Option<Recycler>,
+    value: T,
+) -> Box<A> {
+    match recycler {
+        Some(recycler) => recycler.box_from(value),
+        None => value.into_box(),
+    }
+}
+
+/// Takes the value out of a box, through the recycler if there is one.
+///
+/// # Safety
+///
+/// The value in the box must be of type `T`.
+#[inline]
+pub(crate) unsafe fn unbox<A: ?Sized + Downcast, T: IntoBox<A>>(
+    recycler: &mut Option<Recycler>,
+    value: Box<A>,
+) -> T {
+    match recycler {
+        Some(recycler) => recycler.unbox(value),
+        None => *value.downcast_unchecked::<T>(),
+    }
+}
@@ -0,0 +1,796 @@
+//! A `Map` that keeps its items in a slab, handing out [`Handle`]s for quick access to them.
+
+use core::any::{TypeId, type_name};
+use core::fmt;
+use core::hash::{BuildHasherDefault, Hash, Hasher};
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::mem;
+use core::slice;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::{self, Vec}};
+#[cfg(feature = "std")]
+use std::{collections::HashMap, vec};
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+
+use crate::recycle;
+use crate::storage::{RawCapacity, RawEntry, RawOccupiedEntry, RawStorage, RawVacantEntry};
+use crate::{Downcast, IntoBox, TypeIdHasher};
+
+/// Where each type is in a `RawMap`.
+type IndexMap = HashMap<TypeId, usize, BuildHasherDefault<TypeIdHasher>>;
+
+/// A place in a `RawMap`, and how many times it has been vacated.
+struct SlabSlot<A: ?Sized> {
+    generation: u32,
+    item: Option<(TypeId, Box<A>)>,
+}
+
+impl<A: ?Sized> SlabSlot<A> {
+    #[inline]
+    fn value(&self) -> &A {
+        match self.item {
+            Some((_, ref value)) => value,
+            None => unreachable!("no value in an occupied slot"),
+        }
+    }
+
+    #[inline]
+    fn value_mut(&mut self) -> &mut Box<A> {
+        match self.item {
+            Some((_, ref mut value)) => value,
+            None => unreachable!("no value in an occupied slot"),
+        }
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for SlabSlot<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> SlabSlot<A> {
+        SlabSlot {
+            generation: self.generation,
+            item: self.item.clone(),
+        }
+    }
+}
+
+/// The storage behind a slab [`Map`]: the items in a `Vec` of slots that never move, each with a
+/// generation that counts its removals, with a `TypeId`-keyed `HashMap` of their positions.
+///
+/// A slot that’s vacated is reused for the next new item, except that one whose generation has
+/// reached `u32::MAX` is retired, so that no two items that have been in a slot ever share a
+/// generation.
+pub struct RawMap<A: ?Sized> {
+    slots: Vec<SlabSlot<A>>,
+    free: Vec<usize>,
+    indices: IndexMap,
+}
+
+map_aliases! {
+    /// A collection containing zero or one values for any given type and allowing convenient,
+    /// type-safe access to those values, with [`Handle`]s that get at them without a lookup.
+    ///
+    /// This is <code>anymap::[Map](crate::Map)&lt;A, [RawMap]&lt;A&gt;&gt;</code>, which keeps
+    /// each item in a slot of a `Vec` for as long as it’s in the map, with a `TypeId`-keyed
+    /// `HashMap` of their positions. It has the same API as the other `Map`s, and works with the
+    /// same value types (`Any`, [`CloneAny`](crate::CloneAny), [`DebugAny`](crate::DebugAny),
+    /// *&c.*, with `+ Send` and/or `+ Sync`); the items are visited in the order of their slots.
+    ///
+    /// On top of that, [`insert_with_handle`](crate::Map::insert_with_handle),
+    /// [`handle`](crate::Map::handle) and the entries give you a [`Handle`] to an item, which
+    /// [`get_by_handle`](crate::Map::get_by_handle) and friends use to go straight to its slot.
+    /// A handle stays good while the item is in the map, even when its value is replaced; once
+    /// the item is removed, the handle finds nothing, even if another value of the same type is
+    /// inserted later.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// let mut data = anymap::slab::AnyMap::new();
+    /// let (handle, _) = data.insert_with_handle(1u8);
+    /// assert_eq!(data.get_by_handle(handle), Some(&1));
+    /// data.insert(2u8);
+    /// assert_eq!(data.get_by_handle(handle), Some(&2));
+    /// data.remove::<u8>();
+    /// data.insert(3u8);
+    /// assert_eq!(data.get_by_handle(handle), None);
+    /// ```
+    RawMap
+}
+
+impl<A: ?Sized> RawMap<A> {
+    /// Creates empty storage.
+    #[inline]
+    pub fn new() -> RawMap<A> {
+        RawMap {
+            slots: Vec::new(),
+            free: Vec::new(),
+            indices: IndexMap::default(),
+        }
+    }
+
+    /// Returns the index of the slot holding the item for `type_id`, if there is one.
+    #[inline]
+    pub fn get_index_of(&self, type_id: TypeId) -> Option<usize> {
+        self.indices.get(&type_id).cloned()
+    }
+
+    /// Returns the generation of the slot at `index`, if there is such a slot.
+    #[inline]
+    pub fn generation(&self, index: usize) -> Option<u32> {
+        self.slots.get(index).map(|slot| slot.generation)
+    }
+
+    /// Returns the slot at `index` if it holds an item for `type_id` in the given generation.
+    #[inline]
+    fn slot(&self, index: usize, generation: u32, type_id: TypeId) -> Option<&SlabSlot<A>> {
+        self.slots.get(index).filter(|slot| {
+            slot.generation == generation &&
+                matches!(slot.item, Some((slot_type_id, _)) if slot_type_id == type_id)
+        })
+    }
+
+    /// Returns the slot at `index` if it holds an item for `type_id` in the given generation.
+    #[inline]
+    fn slot_mut(&mut self, index: usize, generation: u32, type_id: TypeId)
+        -> Option<&mut SlabSlot<A>>
+    {
+        self.slots.get_mut(index).filter(|slot| {
+            slot.generation == generation &&
+                matches!(slot.item, Some((slot_type_id, _)) if slot_type_id == type_id)
+        })
+    }
+
+    /// Puts an item that isn’t there already into a free slot, returning the slot’s index.
+    fn occupy(&mut self, type_id: TypeId, value: Box<A>) -> usize {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(SlabSlot { generation: 0, item: None });
+                self.slots.len() - 1
+            },
+        };
+        self.slots[index].item = Some((type_id, value));
+        let _ = self.indices.insert(type_id, index);
+        index
+    }
+
+    /// Takes the item out of the slot at `index`, which must be occupied, starting the slot’s
+    /// next generation. The item must already be gone from `indices`.
+    fn vacate(&mut self, index: usize) -> (TypeId, Box<A>) {
+        let slot = &mut self.slots[index];
+        let item = match slot.item.take() {
+            Some(item) => item,
+            None => unreachable!("vacating a slot that’s not occupied"),
+        };
+        if slot.generation < u32::MAX {
+            slot.generation += 1;
+            self.free.push(index);
+        }
+        item
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for RawMap<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> RawMap<A> {
+        RawMap {
+            slots: self.slots.clone(),
+            free: self.free.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl<A: ?Sized> Default for RawMap<A> {
+    #[inline]
+    fn default() -> RawMap<A> {
+        RawMap::new()
+    }
+}
+
+impl<A: ?Sized + fmt::Debug> fmt::Debug for RawMap<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<A: ?Sized> IntoIterator for RawMap<A> {
+    type Item = (TypeId, Box<A>);
+    type IntoIter = SlabIntoIter<A>;
+
+    #[inline]
+    fn into_iter(self) -> SlabIntoIter<A> {
+        SlabIntoIter {
+            remaining: self.indices.len(),
+            inner: self.slots.into_iter(),
+        }
+    }
+}
+
+unsafe impl<A: ?Sized> RawStorage<A> for RawMap<A> {
+    type Iter<'a> = SlabIter<'a, A> where A: 'a;
+    type IterMut<'a> = SlabIterMut<'a, A> where A: 'a;
+    type Drain<'a> = SlabDrain<'a, A> where A: 'a;
+    type OccupiedEntry<'a> = SlabOccupiedEntry<'a, A> where A: 'a;
+    type VacantEntry<'a> = SlabVacantEntry<'a, A> where A: 'a;
+    type Rebind<B: ?Sized> = RawMap<B>;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.drain().for_each(drop)
+    }
+
+    #[inline]
+    fn get(&self, type_id: TypeId) -> Option<&A> {
+        self.indices.get(&type_id).map(|&index| self.slots[index].value())
+    }
+
+    #[inline]
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+        match self.indices.get(&type_id) {
+            Some(&index) => Some(&mut **self.slots[index].value_mut()),
+            None => None,
+        }
+    }
+
+    #[inline]
+    fn contains_key(&self, type_id: TypeId) -> bool {
+        self.indices.contains_key(&type_id)
+    }
+
+    /// Inserts a value for a `TypeId`, keeping its slot if it’s already there, and returning the
+    /// old value if any.
+    #[inline]
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+        match self.indices.get(&type_id) {
+            Some(&index) => Some(mem::replace(self.slots[index].value_mut(), value)),
+            None => {
+                let _ = self.occupy(type_id, value);
+                None
+            },
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        let index = self.indices.remove(&type_id)?;
+        Some(self.vacate(index).1)
+    }
+
+    #[inline]
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+    {
+        match self.get_index_of(type_id) {
+            Some(index) => RawEntry::Occupied(SlabOccupiedEntry {
+                raw: self,
+                index,
+            }),
+            None => RawEntry::Vacant(SlabVacantEntry {
+                raw: self,
+                type_id,
+            }),
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        SlabIter {
+            inner: self.slots.iter(),
+            remaining: self.indices.len(),
+        }
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        SlabIterMut {
+            inner: self.slots.iter_mut(),
+            remaining: self.indices.len(),
+        }
+    }
+
+    #[inline]
+    fn drain(&mut self) -> Self::Drain<'_> {
+        SlabDrain {
+            index: 0,
+            remaining: self.indices.len(),
+            raw: self,
+        }
+    }
+
+    #[inline]
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        for index in 0..self.slots.len() {
+            let type_id = match self.slots[index].item {
+                Some((type_id, ref mut value)) => {
+                    if f(type_id, &mut **value) {
+                        continue;
+                    }
+                    type_id
+                },
+                None => continue,
+            };
+            let _ = self.indices.remove(&type_id);
+            drop(self.vacate(index));
+        }
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional.saturating_sub(self.free.len()));
+        self.indices.reserve(additional)
+    }
+}
+
+impl<A: ?Sized> RawCapacity<A> for RawMap<A> {
+    #[inline]
+    fn with_capacity(capacity: usize) -> RawMap<A> {
+        RawMap {
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            indices: IndexMap::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        let slots = self.slots.capacity() - self.slots.len() + self.free.len() + self.len();
+        slots.min(self.indices.capacity())
+    }
+
+    /// Shrinks the capacity as much as possible. Vacant slots are kept, so that their
+    /// generations go on counting.
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+        self.free.shrink_to_fit();
+        self.indices.shrink_to_fit()
+    }
+}
+
+/// A view into an occupied place in a slab [`RawMap`].
+pub struct SlabOccupiedEntry<'a, A: ?Sized> {
+    raw: &'a mut RawMap<A>,
+    index: usize,
+}
+
+/// A view into a vacant place in a slab [`RawMap`].
+pub struct SlabVacantEntry<'a, A: ?Sized> {
+    raw: &'a mut RawMap<A>,
+    type_id: TypeId,
+}
+
+impl<'a, A: ?Sized> RawOccupiedEntry<'a, A> for SlabOccupiedEntry<'a, A> {
+    #[inline]
+    fn get(&self) -> &A {
+        self.raw.slots[self.index].value()
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut A {
+        self.raw.slots[self.index].value_mut()
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut A {
+        self.raw.slots[self.index].value_mut()
+    }
+
+    #[inline]
+    fn insert(&mut self, value: Box<A>) -> Box<A> {
+        mem::replace(self.raw.slots[self.index].value_mut(), value)
+    }
+
+    #[inline]
+    fn remove(self) -> Box<A> {
+        let (type_id, value) = self.raw.vacate(self.index);
+        let _ = self.raw.indices.remove(&type_id);
+        value
+    }
+}
+
+impl<'a, A: ?Sized> RawVacantEntry<'a, A> for SlabVacantEntry<'a, A> {
+    #[inline]
+    fn insert(self, value: Box<A>) -> &'a mut A {
+        let index = self.raw.occupy(self.type_id, value);
+        self.raw.slots[index].value_mut()
+    }
+}
+
+/// An iterator over the items of a slab [`RawMap`].
+pub struct SlabIter<'a, A: ?Sized> {
+    inner: slice::Iter<'a, SlabSlot<A>>,
+    remaining: usize,
+}
+
+impl<'a, A: ?Sized> Clone for SlabIter<'a, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        SlabIter {
+            inner: self.inner.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, A: ?Sized> Iterator for SlabIter<'a, A> {
+    type Item = (&'a TypeId, &'a Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a Box<A>)> {
+        let (type_id, value) = self.inner.find_map(|slot| slot.item.as_ref())?;
+        self.remaining -= 1;
+        Some((type_id, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for SlabIter<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for SlabIter<'a, A> {}
+
+/// A mutable iterator over the items of a slab [`RawMap`].
+pub struct SlabIterMut<'a, A: ?Sized> {
+    inner: slice::IterMut<'a, SlabSlot<A>>,
+    remaining: usize,
+}
+
+impl<'a, A: ?Sized> Iterator for SlabIterMut<'a, A> {
+    type Item = (&'a TypeId, &'a mut Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a mut Box<A>)> {
+        let (type_id, value) = self.inner.find_map(|slot| slot.item.as_mut())?;
+        self.remaining -= 1;
+        Some((&*type_id, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for SlabIterMut<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for SlabIterMut<'a, A> {}
+
+/// An owning iterator over the items of a slab [`RawMap`].
+pub struct SlabIntoIter<A: ?Sized> {
+    inner: vec::IntoIter<SlabSlot<A>>,
+    remaining: usize,
+}
+
+impl<A: ?Sized> Iterator for SlabIntoIter<A> {
+    type Item = (TypeId, Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, Box<A>)> {
+        let item = self.inner.find_map(|slot| slot.item)?;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<A: ?Sized> ExactSizeIterator for SlabIntoIter<A> {}
+impl<A: ?Sized> FusedIterator for SlabIntoIter<A> {}
+
+/// A draining iterator over the items of a slab [`RawMap`], which vacates their slots.
+///
+/// If it’s dropped before it’s finished, it vacates the rest anyway.
+pub struct SlabDrain<'a, A: ?Sized> {
+    raw: &'a mut RawMap<A>,
+    index: usize,
+    remaining: usize,
+}
+
+impl<'a, A: ?Sized> Iterator for SlabDrain<'a, A> {
+    type Item = (TypeId, Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(TypeId, Box<A>)> {
+        while self.remaining > 0 {
+            let index = self.index;
+            self.index += 1;
+            if let Some((type_id, _)) = self.raw.slots[index].item {
+                // Each item leaves `indices` as it goes, so that a drain that’s forgotten
+                // leaves the map consistent.
+                let _ = self.raw.indices.remove(&type_id);
+                self.remaining -= 1;
+                return Some(self.raw.vacate(index));
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for SlabDrain<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for SlabDrain<'a, A> {}
+
+impl<'a, A: ?Sized> Drop for SlabDrain<'a, A> {
+    fn drop(&mut self) {
+        self.for_each(drop)
+    }
+}
+
+/// A handle on the item of type `T` in a slab [`Map`], for getting at it without a lookup.
+///
+/// This is the index of the item’s slot and the slot’s generation, which changes whenever an
+/// item is removed from it; so a handle finds its item for as long as the item is in the map
+/// (whatever value it has), and nothing after that. It’s `Copy`, and no bigger than two words.
+///
+/// A handle is checked against the type of the item in the slot, too, so a handle used with a
+/// map other than the one it came from (a clone, say) finds either a value of type `T` or
+/// nothing.
+pub struct Handle<T> {
+    index: usize,
+    generation: u32,
+    type_: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    #[inline]
+    fn new(index: usize, generation: u32) -> Handle<T> {
+        Handle {
+            index,
+            generation,
+            type_: PhantomData,
+        }
+    }
+
+    /// Returns the index of the item’s slot.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the generation of the item’s slot when the handle was made.
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// #[derive] would want T to implement these traits, but the handle doesn’t hold a T.
+impl<T> Clone for Handle<T> {
+    #[inline]
+    fn clone(&self) -> Handle<T> {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    #[inline]
+    fn eq(&self, other: &Handle<T>) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle<{}>({}v{})", type_name::<T>(), self.index, self.generation)
+    }
+}
+
+/// The methods peculiar to a slab `Map`.
+impl<A: ?Sized + Downcast> crate::Map<A, RawMap<A>> {
+    /// Returns a handle on the `T` value in the collection, if there is one.
+    #[inline]
+    pub fn handle<T: IntoBox<A>>(&self) -> Option<Handle<T>> {
+        let index = self.raw.get_index_of(TypeId::of::<T>())?;
+        Some(Handle::new(index, self.raw.slots[index].generation))
+    }
+
+    /// Sets the value stored in the collection for the type `T`, returning a handle on it and
+    /// the value it replaced, if any. If there was one, the handle is the same as before.
+    #[inline]
+    pub fn insert_with_handle<T: IntoBox<A>>(&mut self, value: T) -> (Handle<T>, Option<T>) {
+        match self.entry::<T>() {
+            crate::Entry::Occupied(mut inner) => {
+                let old = inner.insert(value);
+                (inner.handle(), Some(old))
+            },
+            crate::Entry::Vacant(inner) => (inner.insert_with_handle(value), None),
+        }
+    }
+
+    /// Returns a reference to the value that `handle` is on, if it’s still in the collection.
+    #[inline]
+    pub fn get_by_handle<T: IntoBox<A>>(&self, handle: Handle<T>) -> Option<&T> {
+        self.raw.slot(handle.index, handle.generation, TypeId::of::<T>())
+            .map(|slot| unsafe { slot.value().downcast_ref_unchecked() })
+    }
+
+    /// Returns a mutable reference to the value that `handle` is on, if it’s still in the
+    /// collection.
+    #[inline]
+    pub fn get_mut_by_handle<T: IntoBox<A>>(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.raw.slot_mut(handle.index, handle.generation, TypeId::of::<T>())
+            .map(|slot| unsafe { slot.value_mut().downcast_mut_unchecked() })
+    }
+
+    /// Removes the value that `handle` is on from the collection, returning it if it was still
+    /// there or `None` if not.
+    #[inline]
+    pub fn remove_by_handle<T: IntoBox<A>>(&mut self, handle: Handle<T>) -> Option<T> {
+        let _ = self.raw.slot(handle.index, handle.generation, TypeId::of::<T>())?;
+        let _ = self.raw.indices.remove(&TypeId::of::<T>());
+        let (_, value) = self.raw.vacate(handle.index);
+        Some(unsafe { recycle::unbox(&mut self.recycler, value) })
+    }
+}
+
+/// The methods peculiar to an occupied entry in a slab `Map`.
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> crate::OccupiedEntry<'a, A, V, RawMap<A>> {
+    /// Returns a handle on the value in the entry
+    #[inline]
+    pub fn handle(&self) -> Handle<V> {
+        Handle::new(self.inner.index, self.inner.raw.slots[self.inner.index].generation)
+    }
+}
+
+/// The methods peculiar to a vacant entry in a slab `Map`.
+impl<'a, A: ?Sized + Downcast, V: IntoBox<A>> crate::VacantEntry<'a, A, V, RawMap<A>> {
+    /// Sets the value of the entry with the VacantEntry's key,
+    /// and returns a handle on it
+    #[inline]
+    pub fn insert_with_handle(self, value: V) -> Handle<V> {
+        let value = recycle::box_from(self.recycler, value);
+        let raw = self.inner.raw;
+        let index = raw.occupy(self.inner.type_id, value);
+        Handle::new(index, raw.slots[index].generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CloneDebugAny;
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    #[derive(Clone, Debug, PartialEq)] struct A(i32);
+    #[derive(Clone, Debug, PartialEq)] struct B(i32);
+    #[derive(Clone, Debug, PartialEq)] struct C(i32);
+
+    #[test]
+    fn test_handles() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        let (a, old) = map.insert_with_handle(A(1));
+        assert_eq!(old, None);
+        let b = match map.entry::<B>() {
+            Entry::Vacant(view) => view.insert_with_handle(B(2)),
+            Entry::Occupied(_) => unreachable!(),
+        };
+        assert_eq!(map.handle::<A>(), Some(a));
+        assert_eq!(map.get_by_handle(a), Some(&A(1)));
+        map.get_mut_by_handle(b).unwrap().0 += 1;
+        assert_eq!(map.get::<B>(), Some(&B(3)));
+
+        // Replacing a value keeps the handle good.
+        assert_eq!(map.insert_with_handle(A(10)), (a, Some(A(1))));
+        let _ = map.insert(A(11));
+        assert_eq!(map.get_by_handle(a), Some(&A(11)));
+        match map.entry::<A>() {
+            Entry::Occupied(view) => assert_eq!(view.handle(), a),
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        // Removing it doesn’t, even once the slot is reused for the same type.
+        assert_eq!(map.remove_by_handle(a), Some(A(11)));
+        assert_eq!(map.get_by_handle(a), None);
+        assert_eq!(map.remove_by_handle(a), None);
+        let (a2, _) = map.insert_with_handle(A(12));
+        assert_eq!(a2.index(), a.index());
+        assert_ne!(a2, a);
+        assert_eq!(map.get_by_handle(a), None);
+        assert_eq!(map.get_by_handle(a2), Some(&A(12)));
+        assert_eq!(map.type_name(TypeId::of::<A>()), Some(type_name::<A>()));
+
+        // A clone keeps the slots, so handles work on it too.
+        let clone = map.clone();
+        assert_eq!(clone.get_by_handle(b), Some(&B(3)));
+
+        // A handle only ever finds a value of its own type.
+        let mut other: Map<dyn CloneDebugAny> = Map::new();
+        let _ = other.insert(C(4));
+        assert_eq!(other.get_by_handle(a2), None);
+
+        let _ = map.insert(C(5));
+        map.retain(|type_id, _| type_id != TypeId::of::<B>());
+        assert_eq!(map.get_by_handle(b), None);
+        let c = map.handle::<C>().unwrap();
+        let mut drain = map.drain();
+        assert!(drain.next().is_some());
+        drop(drain);
+        assert!(map.is_empty());
+        assert_eq!(map.get_by_handle(a2), None);
+        assert_eq!(map.get_by_handle(c), None);
+        let _ = map.insert(C(6));
+        map.clear();
+        assert_eq!(map.as_raw().slots.len(), 3);
+        assert_eq!(map.into_iter().count(), 0);
+        assert_eq!(clone.iter().map(|(type_id, _)| type_id).collect::<Vec<_>>().len(), 2);
+    }
+
+    #[test]
+    fn test_retired_slot() {
+        let mut map: AnyMap = Map::new();
+        let (a, _) = map.insert_with_handle(A(1));
+        map.raw.slots[a.index()].generation = u32::MAX;
+        let a = map.handle::<A>().unwrap();
+        assert_eq!(map.remove::<A>(), Some(A(1)));
+        assert_eq!(map.raw.generation(a.index()), Some(u32::MAX));
+        let (b, _) = map.insert_with_handle(B(2));
+        assert_ne!(b.index(), a.index());
+        let _ = map.insert(A(3));
+        assert_eq!(map.get_by_handle(a), None);
+    }
+
+    #[test]
+    fn test_remove_by_handle() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        map.set_recycling(true);
+        let (a, _) = map.insert_with_handle(A(1));
+        let _ = map.insert(B(2));
+        let ptr = map.get::<A>().unwrap() as *const A;
+        assert_eq!(map.remove_by_handle(a), Some(A(1)));
+        assert!(!map.contains::<A>());
+        assert_eq!(map.type_name(TypeId::of::<A>()), None);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.iter().count(), 1);
+        assert_eq!(map.recycled_len(), 1);
+        let _ = map.insert(A(3));
+        assert_eq!(map.recycled_len(), 0);
+        assert_eq!(map.get::<A>().unwrap() as *const A, ptr);
+    }
+
+    #[test]
+    fn test_forgotten_drain() {
+        let mut map: Map<dyn CloneDebugAny> = Map::new();
+        let _ = map.insert_all((A(1), B(2), C(3)));
+        let mut drain = map.drain();
+        assert!(drain.next().is_some());
+        core::mem::forget(drain);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.iter().len(), 2);
+        assert_eq!(map.iter().count(), 2);
+        assert_eq!(map.iter_mut().count(), 2);
+        assert_eq!(map.drain().count(), 2);
+        assert!(map.is_empty());
+    }
+}
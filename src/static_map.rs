@@ -0,0 +1,528 @@
+use core::any::{Any, TypeId};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+/// A slot’s bytes, aligned enough for most types.
+///
+/// They’re in an `UnsafeCell` because the value may have interior mutability (a `Cell`, say),
+/// and so be changed through a shared reference to the slot.
+#[repr(C, align(16))]
+struct Buffer<const BYTES: usize>(UnsafeCell<[MaybeUninit<u8>; BYTES]>);
+
+impl<const BYTES: usize> Buffer<BYTES> {
+    /// Returns a pointer to the start of the bytes.
+    #[inline]
+    fn as_ptr(&self) -> *mut u8 {
+        self.0.get().cast()
+    }
+}
+
+/// One value in a `StaticAnyMap`, with what’s needed to drop it.
+struct Slot<const BYTES: usize> {
+    type_id: TypeId,
+    drop: unsafe fn(*mut u8),
+    value: Buffer<BYTES>,
+}
+
+/// Drops the `T` at `ptr`, for `Slot::drop`.
+unsafe fn drop_value<T>(ptr: *mut u8) {
+    ptr::drop_in_place(ptr.cast::<T>())
+}
+
+impl<const BYTES: usize> Slot<BYTES> {
+    /// Moves `value` into a new slot. The caller must have checked that `T` fits.
+    #[inline]
+    fn new<T: Any>(value: T) -> Slot<BYTES> {
+        debug_assert!(mem::size_of::<T>() <= BYTES && mem::align_of::<T>() <= MAX_ALIGN);
+        let slot = Slot {
+            type_id: TypeId::of::<T>(),
+            drop: drop_value::<T>,
+            value: Buffer(UnsafeCell::new([MaybeUninit::uninit(); BYTES])),
+        };
+        unsafe { ptr::write(slot.value.as_ptr().cast::<T>(), value) };
+        slot
+    }
+
+    /// Returns the value as a `T`, which must be its type.
+    #[inline]
+    unsafe fn as_ref<T>(&self) -> &T {
+        &*self.value.as_ptr().cast::<T>()
+    }
+
+    /// Returns the value mutably as a `T`, which must be its type.
+    #[inline]
+    unsafe fn as_mut<T>(&mut self) -> &mut T {
+        &mut *self.value.as_ptr().cast::<T>()
+    }
+
+    /// Moves the value out as a `T`, which must be its type.
+    #[inline]
+    unsafe fn into_inner<T>(self) -> T {
+        let value = ptr::read(self.value.as_ptr().cast::<T>());
+        mem::forget(self);
+        value
+    }
+}
+
+impl<const BYTES: usize> Drop for Slot<BYTES> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.value.as_ptr()) }
+    }
+}
+
+/// The greatest alignment a value in a `StaticAnyMap` can have; see [`StaticAnyMap::MAX_ALIGN`].
+const MAX_ALIGN: usize = mem::align_of::<Buffer<0>>();
+
+/// Checks that a `T` fits in a slot of `BYTES` bytes, handing the value back if not.
+#[inline]
+fn check_fits<T, const BYTES: usize>(value: T) -> Result<T, InsertError<T>> {
+    if mem::size_of::<T>() > BYTES {
+        Err(InsertError::TooBig(value))
+    } else if mem::align_of::<T>() > MAX_ALIGN {
+        Err(InsertError::Overaligned(value))
+    } else {
+        Ok(value)
+    }
+}
+
+/// A collection containing zero or one values for any given type, like a `Map`,
+/// but storing up to `N` values inline, each in a slot of `BYTES` bytes, without allocating.
+///
+/// This needs neither std nor `alloc`, for targets without an allocator. It doesn’t box its
+/// values, so there’s no choice of value type: values can be of any `'static` type that fits in a
+/// slot, at most `BYTES` bytes in size and [`MAX_ALIGN`](StaticAnyMap::MAX_ALIGN) in alignment.
+/// Inserting a value that doesn’t fit, or a value of a new type when all `N` slots are taken,
+/// fails with an [`InsertError`], which hands the value back. Each value is dropped properly when
+/// it’s removed or replaced, or when the collection is cleared or dropped.
+///
+/// Looking a value up scans the slots, so this is best kept to a handful of small values. Since
+/// the values needn’t be `Send` or `Sync`, neither is the collection.
+///
+/// ## Example
+///
+/// ```rust
+/// use anymap::{InsertError, StaticAnyMap};
+///
+/// let mut data = StaticAnyMap::<2, 16>::new();
+/// assert_eq!(data.insert(42i32).unwrap(), None);
+/// assert_eq!(data.insert("forty-two").unwrap(), None);
+/// assert_eq!(data.get::<i32>(), Some(&42));
+/// assert_eq!(data.insert(43i32).unwrap(), Some(42));
+/// assert!(matches!(data.insert(42u8), Err(InsertError::Full(42))));
+/// assert!(matches!(data.remove::<&str>(), Some("forty-two")));
+/// assert!(matches!(data.insert([0u8; 17]), Err(InsertError::TooBig(_))));
+/// ```
+pub struct StaticAnyMap<const N: usize, const BYTES: usize> {
+    slots: [Option<Slot<BYTES>>; N],
+    len: usize,
+    marker: PhantomData<*mut ()>,
+}
+
+impl<const N: usize, const BYTES: usize> Default for StaticAnyMap<N, BYTES> {
+    #[inline]
+    fn default() -> StaticAnyMap<N, BYTES> {
+        StaticAnyMap::new()
+    }
+}
+
+impl<const N: usize, const BYTES: usize> fmt::Debug for StaticAnyMap<N, BYTES> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.slots.iter().flatten().map(|slot| slot.type_id)).finish()
+    }
+}
+
+impl<const N: usize, const BYTES: usize> StaticAnyMap<N, BYTES> {
+    /// The greatest alignment that a value can have to be stored in a `StaticAnyMap`.
+    pub const MAX_ALIGN: usize = MAX_ALIGN;
+
+    /// Create an empty collection.
+    #[inline]
+    pub const fn new() -> StaticAnyMap<N, BYTES> {
+        StaticAnyMap {
+            slots: [const { None }; N],
+            len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of items the collection can hold, which is `N`.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of items in the collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no items in the collection.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all items from the collection, dropping them.
+    #[inline]
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            *slot = None;
+        }
+        self.len = 0;
+    }
+
+    /// Returns the slot holding the `T`, if there is one.
+    #[inline]
+    fn find<T: Any>(&self) -> Option<&Slot<BYTES>> {
+        self.slots.iter().flatten().find(|slot| slot.type_id == TypeId::of::<T>())
+    }
+
+    /// Returns a reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.find::<T>().map(|slot| unsafe { slot.as_ref::<T>() })
+    }
+
+    /// Returns a mutable reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        match self.entry::<T>() {
+            StaticEntry::Occupied(inner) => Some(inner.into_mut()),
+            StaticEntry::Vacant(_) => None,
+        }
+    }
+
+    /// Sets the value stored in the collection for the type `T`.
+    /// If the collection already had a value of type `T`, that value is returned.
+    /// Otherwise, `None` is returned.
+    ///
+    /// # Errors
+    ///
+    /// Fails, handing the value back, if a `T` doesn’t fit in a slot, or if there isn’t already
+    /// a `T` and every slot is taken.
+    #[inline]
+    pub fn insert<T: Any>(&mut self, value: T) -> Result<Option<T>, InsertError<T>> {
+        match self.entry::<T>() {
+            StaticEntry::Occupied(mut inner) => Ok(Some(inner.insert(value))),
+            StaticEntry::Vacant(inner) => inner.insert(value).map(|_| None),
+        }
+    }
+
+    /// Removes the `T` value from the collection,
+    /// returning it if there was one or `None` if there was not.
+    #[inline]
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        match self.entry::<T>() {
+            StaticEntry::Occupied(inner) => Some(inner.remove()),
+            StaticEntry::Vacant(_) => None,
+        }
+    }
+
+    /// Returns true if the collection contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: Any>(&self) -> bool {
+        self.find::<T>().is_some()
+    }
+
+    /// Gets the entry for the given type in the collection for in-place manipulation
+    #[inline]
+    pub fn entry<T: Any>(&mut self) -> StaticEntry<'_, T, N, BYTES> {
+        let mut free = None;
+        for slot in &mut self.slots {
+            if matches!(slot, Some(slot) if slot.type_id == TypeId::of::<T>()) {
+                return StaticEntry::Occupied(OccupiedStaticEntry {
+                    slot,
+                    len: &mut self.len,
+                    type_: PhantomData,
+                });
+            } else if slot.is_none() && free.is_none() {
+                free = Some(slot);
+            }
+        }
+        StaticEntry::Vacant(VacantStaticEntry {
+            slot: free,
+            len: &mut self.len,
+            type_: PhantomData,
+        })
+    }
+}
+
+/// A view into a single occupied location in a `StaticAnyMap`.
+pub struct OccupiedStaticEntry<'a, V: 'a, const N: usize, const BYTES: usize> {
+    slot: &'a mut Option<Slot<BYTES>>,
+    len: &'a mut usize,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single empty location in a `StaticAnyMap`, along with a free slot if there is
+/// one.
+pub struct VacantStaticEntry<'a, V: 'a, const N: usize, const BYTES: usize> {
+    slot: Option<&'a mut Option<Slot<BYTES>>>,
+    len: &'a mut usize,
+    type_: PhantomData<V>,
+}
+
+/// A view into a single location in a `StaticAnyMap`, which may be vacant or occupied.
+pub enum StaticEntry<'a, V: 'a, const N: usize, const BYTES: usize> {
+    /// An occupied Entry
+    Occupied(OccupiedStaticEntry<'a, V, N, BYTES>),
+    /// A vacant Entry
+    Vacant(VacantStaticEntry<'a, V, N, BYTES>),
+}
+
+impl<'a, V: Any, const N: usize, const BYTES: usize> StaticEntry<'a, V, N, BYTES> {
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry, or an error if it was empty and there was
+    /// no room for the default.
+    #[inline]
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, InsertError<V>> {
+        match self {
+            StaticEntry::Occupied(inner) => Ok(inner.into_mut()),
+            StaticEntry::Vacant(inner) => inner.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if
+    /// empty, and returns a mutable reference to the value in the entry, or an error if it was
+    /// empty and there was no room for the default.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<&'a mut V, InsertError<V>> {
+        match self {
+            StaticEntry::Occupied(inner) => Ok(inner.into_mut()),
+            StaticEntry::Vacant(inner) => inner.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty, and returns
+    /// a mutable reference to the value in the entry, or an error if it was empty and there was
+    /// no room for the default.
+    #[inline]
+    pub fn or_default(self) -> Result<&'a mut V, InsertError<V>> where V: Default {
+        match self {
+            StaticEntry::Occupied(inner) => Ok(inner.into_mut()),
+            StaticEntry::Vacant(inner) => inner.insert(Default::default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts
+    /// into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            StaticEntry::Occupied(mut inner) => {
+                f(inner.get_mut());
+                StaticEntry::Occupied(inner)
+            },
+            StaticEntry::Vacant(inner) => StaticEntry::Vacant(inner),
+        }
+    }
+}
+
+impl<'a, V: Any, const N: usize, const BYTES: usize> OccupiedStaticEntry<'a, V, N, BYTES> {
+    /// Gets a reference to the value in the entry
+    #[inline]
+    pub fn get(&self) -> &V {
+        match self.slot.as_ref() {
+            Some(slot) => unsafe { slot.as_ref() },
+            None => unreachable!(),
+        }
+    }
+
+    /// Gets a mutable reference to the value in the entry
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        match self.slot.as_mut() {
+            Some(slot) => unsafe { slot.as_mut() },
+            None => unreachable!(),
+        }
+    }
+
+    /// Converts the OccupiedStaticEntry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the collection itself
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        match self.slot {
+            Some(slot) => unsafe { slot.as_mut() },
+            None => unreachable!(),
+        }
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry, and returns it
+    #[inline]
+    pub fn remove(self) -> V {
+        *self.len -= 1;
+        match self.slot.take() {
+            Some(slot) => unsafe { slot.into_inner() },
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<'a, V: Any, const N: usize, const BYTES: usize> VacantStaticEntry<'a, V, N, BYTES> {
+    /// Sets the value of the entry with the VacantStaticEntry's key,
+    /// and returns a mutable reference to it
+    ///
+    /// # Errors
+    ///
+    /// Fails, handing the value back, if a `V` doesn’t fit in a slot, or if every slot is taken.
+    #[inline]
+    pub fn insert(self, value: V) -> Result<&'a mut V, InsertError<V>> {
+        let value = check_fits::<V, BYTES>(value)?;
+        match self.slot {
+            Some(slot) => {
+                *self.len += 1;
+                Ok(unsafe { slot.insert(Slot::new(value)).as_mut() })
+            },
+            None => Err(InsertError::Full(value)),
+        }
+    }
+}
+
+/// The error from inserting a value into a [`StaticAnyMap`] that has no room for it, which hands
+/// the value back.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InsertError<T> {
+    /// Every slot is taken by a value of another type.
+    Full(T),
+    /// The value is bigger than a slot.
+    TooBig(T),
+    /// The value needs greater alignment than [`StaticAnyMap::MAX_ALIGN`].
+    Overaligned(T),
+}
+
+impl<T> InsertError<T> {
+    /// Returns the value that couldn’t be inserted.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self {
+            InsertError::Full(value) |
+            InsertError::TooBig(value) |
+            InsertError::Overaligned(value) => value,
+        }
+    }
+}
+
+// Like std’s TrySendError, this doesn’t need the value to be Debug.
+impl<T> fmt::Debug for InsertError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            InsertError::Full(_) => "Full(..)",
+            InsertError::TooBig(_) => "TooBig(..)",
+            InsertError::Overaligned(_) => "Overaligned(..)",
+        })
+    }
+}
+
+impl<T> fmt::Display for InsertError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            InsertError::Full(_) => "every slot in the map is taken",
+            InsertError::TooBig(_) => "the value is bigger than the map’s slots",
+            InsertError::Overaligned(_) => "the value needs greater alignment than the map’s slots",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> std::error::Error for InsertError<T> {}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::{Cell, RefCell};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use super::*;
+
+    #[derive(Debug, PartialEq)] struct A(u32);
+    #[derive(Debug, PartialEq)] struct B(u64);
+    #[derive(Debug, PartialEq)] struct C(u8);
+    #[derive(Debug, PartialEq)] #[repr(align(32))] struct Overaligned(u8);
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq)]
+    struct Dropper(u8);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) {
+            let _ = DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_static_map() {
+        let mut map = StaticAnyMap::<3, 8>::new();
+        assert_eq!(map.capacity(), 3);
+        assert!(map.is_empty());
+        assert_eq!(map.insert(A(1)).unwrap(), None);
+        assert_eq!(map.insert(B(2)).unwrap(), None);
+        assert_eq!(map.insert(A(10)).unwrap(), Some(A(1)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<A>(), Some(&A(10)));
+        assert_eq!(map.get::<C>(), None);
+        map.get_mut::<B>().unwrap().0 += 1;
+        assert_eq!(map.get::<B>(), Some(&B(3)));
+        assert!(map.contains::<B>());
+
+        assert_eq!(map.insert([0u8; 9]).unwrap_err(), InsertError::TooBig([0u8; 9]));
+        assert_eq!(map.insert(Overaligned(1)).unwrap_err().into_inner(), Overaligned(1));
+        assert_eq!(map.insert(()).unwrap(), None);
+        assert_eq!(map.insert(C(4)).unwrap_err(), InsertError::Full(C(4)));
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.remove::<B>(), Some(B(3)));
+        assert_eq!(map.remove::<B>(), None);
+        assert_eq!(*map.entry::<C>().or_insert(C(5)).unwrap(), C(5));
+        assert_eq!(*map.entry::<C>().and_modify(|c| c.0 += 1).or_insert(C(0)).unwrap(), C(6));
+        assert!(map.entry::<u16>().or_default().is_err());
+        match map.entry::<A>() {
+            StaticEntry::Occupied(mut view) => {
+                assert_eq!(view.insert(A(11)), A(10));
+                assert_eq!(view.remove(), A(11));
+            },
+            StaticEntry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(*map.entry::<u16>().or_default().unwrap(), 0);
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_interior_mutability() {
+        let mut map = StaticAnyMap::<2, 16>::new();
+        assert!(map.insert(Cell::new(1u8)).unwrap().is_none());
+        assert!(map.insert(RefCell::new(2u16)).unwrap().is_none());
+        map.get::<Cell<u8>>().unwrap().set(3);
+        *map.get::<RefCell<u16>>().unwrap().borrow_mut() += 2;
+        assert_eq!(map.get::<Cell<u8>>().map(Cell::get), Some(3));
+        assert_eq!(map.remove::<RefCell<u16>>().map(RefCell::into_inner), Some(4));
+    }
+
+    #[test]
+    fn test_static_map_drops() {
+        let mut map = StaticAnyMap::<2, 1>::default();
+        assert!(map.insert(Dropper(1)).unwrap().is_none());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+        drop(map.insert(Dropper(2)));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        drop(map.remove::<Dropper>());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        assert!(map.insert(Dropper(3)).unwrap().is_none());
+        map.clear();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        assert!(map.insert(Dropper(4)).unwrap().is_none());
+        drop(map);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+}
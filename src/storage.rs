@@ -0,0 +1,797 @@
+//! The storage behind a [`Map`](crate::Map), and the [`RawStorage`] trait for supplying your own.
+//!
+//! A `Map<A, S>` keeps its values in an `S`, which only has to act as a map from `TypeId` to
+//! `Box<A>`; the `Map` takes care of the values’ types. The storage decides how the items are
+//! found and in what order they are visited. These are provided:
+//!
+//! - `std::collections::HashMap<TypeId, Box<A>, _>`, with the std feature, in arbitrary order
+//!   (the default, aliased as `anymap::RawMap` with `TypeIdHasher`);
+//! - `hashbrown::HashMap<TypeId, Box<A>, _>`, with the hashbrown feature, likewise
+//!   (aliased as `anymap::hashbrown::RawMap`);
+//! - `BTreeMap<TypeId, Box<A>>`, in order of `TypeId` ([`anymap::btree`](crate::btree));
+//! - [`SortedVec`], also in order of `TypeId`, for small maps that don’t want a hash table (the
+//!   default with neither std nor hashbrown);
+//! - with std or hashbrown, `anymap::indexed::RawMap`, in insertion order;
+//! - with std or hashbrown, `anymap::adaptive::RawMap`, which searches an inline array
+//!   while it holds only a few items, and uses a hash map for more;
+//! - and, with std or hashbrown, `anymap::slab::RawMap`, in slots that never move, so that
+//!   handles on its items can skip the lookup.
+
+use core::any::TypeId;
+use core::fmt;
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+use core::hash::BuildHasher;
+use core::iter::FusedIterator;
+use core::slice;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::btree_map::{self, BTreeMap}, vec::{self, Vec}};
+#[cfg(feature = "std")]
+use std::{collections::btree_map::{self, BTreeMap}, vec};
+
+/// The storage that a [`Map`](crate::Map) uses if you don’t say otherwise.
+///
+/// With the std feature, as in this build, this is `std::collections::HashMap`, with
+/// [`TypeIdHasher`](crate::TypeIdHasher).
+#[cfg(feature = "std")]
+pub type DefaultStorage<A> = crate::RawMap<A>;
+
+/// The storage that a [`Map`](crate::Map) uses if you don’t say otherwise.
+///
+/// With the hashbrown feature but not std, as in this build, this is `hashbrown::HashMap`, with
+/// [`TypeIdHasher`](crate::TypeIdHasher).
+#[cfg(all(not(feature = "std"), feature = "hashbrown"))]
+pub type DefaultStorage<A> = crate::hashbrown::RawMap<A>;
+
+/// The storage that a [`Map`](crate::Map) uses if you don’t say otherwise.
+///
+/// With neither the std nor the hashbrown feature, as in this build, this is [`SortedVec`], which
+/// needs nothing but `alloc`.
+#[cfg(not(any(feature = "std", feature = "hashbrown")))]
+pub type DefaultStorage<A> = SortedVec<A>;
+
+/// A collection of boxed values, each stored under a `TypeId`, which can back a
+/// [`Map`](crate::Map).
+///
+/// See [the module documentation](self) for the implementations provided. To use your own
+/// collection, implement this for it and use `Map<A, YourStorage>`.
+///
+/// This is mostly a subset of the API of `HashMap<TypeId, Box<A>>`, but with `TypeId`s passed by
+/// value, and values handed out as `&A` and `&mut A` rather than references to the boxes.
+///
+/// # Safety
+///
+/// `Map` trusts that a value stored under a `TypeId` is of that type, and so doesn’t check before
+/// handing out references to it as that type. So this must really behave as a map:
+///
+/// - `get`, `get_mut`, `remove` and `entry` must find the value most recently stored under the
+///   given `TypeId` (by `insert`, or through an entry) and not removed since, or nothing;
+/// - the iterators, including `into_iter` and `drain`, must yield each value stored once, with
+///   the `TypeId` it was stored under, and `retain` must likewise pass each value with its
+///   `TypeId`;
+/// - values stored under different `TypeId`s must be different values, so that mutable
+///   references to them don’t alias.
+pub unsafe trait RawStorage<A: ?Sized>:
+    Default + IntoIterator<Item = (TypeId, Box<A>), IntoIter: ExactSizeIterator + FusedIterator>
+{
+    /// An iterator over the items, created by [`iter`](RawStorage::iter).
+    type Iter<'a>: ExactSizeIterator<Item = (&'a TypeId, &'a Box<A>)> + FusedIterator + Clone
+    where Self: 'a, A: 'a;
+
+    /// A mutable iterator over the items, created by [`iter_mut`](RawStorage::iter_mut).
+    type IterMut<'a>: ExactSizeIterator<Item = (&'a TypeId, &'a mut Box<A>)> + FusedIterator
+    where Self: 'a, A: 'a;
+
+    /// A draining iterator over the items, created by [`drain`](RawStorage::drain).
+    type Drain<'a>: ExactSizeIterator<Item = (TypeId, Box<A>)> + FusedIterator
+    where Self: 'a, A: 'a;
+
+    /// A view into an occupied place, as found by [`entry`](RawStorage::entry).
+    type OccupiedEntry<'a>: RawOccupiedEntry<'a, A> where Self: 'a, A: 'a;
+
+    /// A view into a vacant place, as found by [`entry`](RawStorage::entry).
+    type VacantEntry<'a>: RawVacantEntry<'a, A> where Self: 'a, A: 'a;
+
+    /// The same sort of storage, but holding `B` rather than `A`, for
+    /// [`Map::upcast`](crate::Map::upcast).
+    type Rebind<B: ?Sized>: RawStorage<B>;
+
+    /// Returns the number of items.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no items.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all items.
+    fn clear(&mut self);
+
+    /// Returns a reference to the value stored under `type_id`, if there is one.
+    fn get(&self, type_id: TypeId) -> Option<&A>;
+
+    /// Returns a mutable reference to the value stored under `type_id`, if there is one.
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A>;
+
+    /// Returns true if there is a value stored under `type_id`.
+    #[inline]
+    fn contains_key(&self, type_id: TypeId) -> bool {
+        self.get(type_id).is_some()
+    }
+
+    /// Stores `value` under `type_id`, returning the value it replaces, if any.
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>>;
+
+    /// Removes the value stored under `type_id`, returning it if there was one.
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>>;
+
+    /// Gets the place for `type_id`, for in-place manipulation.
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>;
+
+    /// An iterator visiting all items, yielding each `TypeId` and a reference to its value.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// An iterator visiting all items, yielding each `TypeId` and a mutable reference to its
+    /// value.
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+
+    /// Removes all items, returning them as an iterator.
+    fn drain(&mut self) -> Self::Drain<'_>;
+
+    /// Retains only the items for which `f(type_id, &mut value)` returns `true`.
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, f: F);
+
+    /// Prepares for at least `additional` more items to be inserted.
+    ///
+    /// This is only a hint, and does nothing by default. Storage that has capacity to manage
+    /// should implement [`RawCapacity`] as well.
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+}
+
+/// [`RawStorage`] that can allocate space in advance, giving its [`Map`](crate::Map) the
+/// `with_capacity`, `capacity`, `reserve` and `shrink_to_fit` methods.
+///
+/// Implementations should implement [`RawStorage::reserve`] properly, too.
+pub trait RawCapacity<A: ?Sized>: RawStorage<A> {
+    /// Creates empty storage with space for at least `capacity` items.
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Returns the number of items that can be held without reallocating.
+    fn capacity(&self) -> usize;
+
+    /// Shrinks the capacity as much as possible.
+    fn shrink_to_fit(&mut self);
+}
+
+/// A place in a [`RawStorage`], which may be vacant or occupied, as found by
+/// [`entry`](RawStorage::entry).
+pub enum RawEntry<O, V> {
+    /// An occupied place
+    Occupied(O),
+    /// A vacant place
+    Vacant(V),
+}
+
+/// A view into an occupied place in a [`RawStorage`].
+pub trait RawOccupiedEntry<'a, A: ?Sized + 'a> {
+    /// Gets a reference to the value
+    fn get(&self) -> &A;
+
+    /// Gets a mutable reference to the value
+    fn get_mut(&mut self) -> &mut A;
+
+    /// Converts the entry into a mutable reference to the value, with a lifetime bound to the
+    /// storage itself
+    fn into_mut(self) -> &'a mut A;
+
+    /// Sets the value, and returns the old value
+    fn insert(&mut self, value: Box<A>) -> Box<A>;
+
+    /// Takes the value out of the storage, and returns it
+    fn remove(self) -> Box<A>;
+}
+
+/// A view into a vacant place in a [`RawStorage`].
+pub trait RawVacantEntry<'a, A: ?Sized + 'a> {
+    /// Stores the value in the place, and returns a mutable reference to it
+    fn insert(self, value: Box<A>) -> &'a mut A;
+}
+
+/// Implements `RawStorage` and `RawCapacity` for one of the `HashMap`s.
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+macro_rules! hash_map_storage {
+    ($($parent:ident)::+ $(, $entry_generics:ident)?) => {
+        // Whatever the hasher does, the map checks the keys for equality, so it remains a map.
+        unsafe impl<A: ?Sized, H: BuildHasher + Default> RawStorage<A>
+            for ::$($parent)::+::hash_map::HashMap<TypeId, Box<A>, H>
+        {
+            type Iter<'a> = ::$($parent)::+::hash_map::Iter<'a, TypeId, Box<A>>
+            where Self: 'a, A: 'a;
+            type IterMut<'a> = ::$($parent)::+::hash_map::IterMut<'a, TypeId, Box<A>>
+            where Self: 'a, A: 'a;
+            type Drain<'a> = ::$($parent)::+::hash_map::Drain<'a, TypeId, Box<A>>
+            where Self: 'a, A: 'a;
+            type OccupiedEntry<'a> =
+                ::$($parent)::+::hash_map::OccupiedEntry<'a, TypeId, Box<A> $(, $entry_generics)?>
+            where Self: 'a, A: 'a;
+            type VacantEntry<'a> =
+                ::$($parent)::+::hash_map::VacantEntry<'a, TypeId, Box<A> $(, $entry_generics)?>
+            where Self: 'a, A: 'a;
+            type Rebind<B: ?Sized> = ::$($parent)::+::hash_map::HashMap<TypeId, Box<B>, H>;
+
+            #[inline]
+            fn len(&self) -> usize {
+                self.len()
+            }
+
+            #[inline]
+            fn clear(&mut self) {
+                self.clear()
+            }
+
+            #[inline]
+            fn get(&self, type_id: TypeId) -> Option<&A> {
+                self.get(&type_id).map(|value| &**value)
+            }
+
+            #[inline]
+            fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+                self.get_mut(&type_id).map(|value| &mut **value)
+            }
+
+            #[inline]
+            fn contains_key(&self, type_id: TypeId) -> bool {
+                self.contains_key(&type_id)
+            }
+
+            #[inline]
+            fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+                self.insert(type_id, value)
+            }
+
+            #[inline]
+            fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+                self.remove(&type_id)
+            }
+
+            #[inline]
+            fn entry(&mut self, type_id: TypeId)
+                -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+            {
+                match self.entry(type_id) {
+                    ::$($parent)::+::hash_map::Entry::Occupied(e) => RawEntry::Occupied(e),
+                    ::$($parent)::+::hash_map::Entry::Vacant(e) => RawEntry::Vacant(e),
+                }
+            }
+
+            #[inline]
+            fn iter(&self) -> Self::Iter<'_> {
+                self.iter()
+            }
+
+            #[inline]
+            fn iter_mut(&mut self) -> Self::IterMut<'_> {
+                self.iter_mut()
+            }
+
+            #[inline]
+            fn drain(&mut self) -> Self::Drain<'_> {
+                self.drain()
+            }
+
+            #[inline]
+            fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+                self.retain(|&type_id, value| f(type_id, &mut **value))
+            }
+
+            #[inline]
+            fn reserve(&mut self, additional: usize) {
+                self.reserve(additional)
+            }
+        }
+
+        impl<A: ?Sized, H: BuildHasher + Default> RawCapacity<A>
+            for ::$($parent)::+::hash_map::HashMap<TypeId, Box<A>, H>
+        {
+            #[inline]
+            fn with_capacity(capacity: usize) -> Self {
+                Self::with_capacity_and_hasher(capacity, H::default())
+            }
+
+            #[inline]
+            fn capacity(&self) -> usize {
+                self.capacity()
+            }
+
+            #[inline]
+            fn shrink_to_fit(&mut self) {
+                self.shrink_to_fit()
+            }
+        }
+
+        impl<'a, A: ?Sized $(, $entry_generics)?> RawOccupiedEntry<'a, A>
+            for ::$($parent)::+::hash_map::OccupiedEntry<'a, TypeId, Box<A> $(, $entry_generics)?>
+        {
+            #[inline]
+            fn get(&self) -> &A {
+                &**self.get()
+            }
+
+            #[inline]
+            fn get_mut(&mut self) -> &mut A {
+                &mut **self.get_mut()
+            }
+
+            #[inline]
+            fn into_mut(self) -> &'a mut A {
+                &mut **self.into_mut()
+            }
+
+            #[inline]
+            fn insert(&mut self, value: Box<A>) -> Box<A> {
+                self.insert(value)
+            }
+
+            #[inline]
+            fn remove(self) -> Box<A> {
+                self.remove()
+            }
+        }
+
+        impl<'a, A: ?Sized $(, $entry_generics: BuildHasher)?> RawVacantEntry<'a, A>
+            for ::$($parent)::+::hash_map::VacantEntry<'a, TypeId, Box<A> $(, $entry_generics)?>
+        {
+            #[inline]
+            fn insert(self, value: Box<A>) -> &'a mut A {
+                &mut **self.insert(value)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+hash_map_storage!(std::collections);
+
+#[cfg(feature = "hashbrown")]
+hash_map_storage!(hashbrown, H);
+
+unsafe impl<A: ?Sized> RawStorage<A> for BTreeMap<TypeId, Box<A>> {
+    type Iter<'a> = btree_map::Iter<'a, TypeId, Box<A>> where A: 'a;
+    type IterMut<'a> = btree_map::IterMut<'a, TypeId, Box<A>> where A: 'a;
+    // BTreeMap has no drain, but taking all its items leaves no allocation behind anyway.
+    type Drain<'a> = btree_map::IntoIter<TypeId, Box<A>> where A: 'a;
+    type OccupiedEntry<'a> = btree_map::OccupiedEntry<'a, TypeId, Box<A>> where A: 'a;
+    type VacantEntry<'a> = btree_map::VacantEntry<'a, TypeId, Box<A>> where A: 'a;
+    type Rebind<B: ?Sized> = BTreeMap<TypeId, Box<B>>;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    #[inline]
+    fn get(&self, type_id: TypeId) -> Option<&A> {
+        self.get(&type_id).map(|value| &**value)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+        self.get_mut(&type_id).map(|value| &mut **value)
+    }
+
+    #[inline]
+    fn contains_key(&self, type_id: TypeId) -> bool {
+        self.contains_key(&type_id)
+    }
+
+    #[inline]
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+        self.insert(type_id, value)
+    }
+
+    #[inline]
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        self.remove(&type_id)
+    }
+
+    #[inline]
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+    {
+        match self.entry(type_id) {
+            btree_map::Entry::Occupied(e) => RawEntry::Occupied(e),
+            btree_map::Entry::Vacant(e) => RawEntry::Vacant(e),
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.iter_mut()
+    }
+
+    #[inline]
+    fn drain(&mut self) -> Self::Drain<'_> {
+        core::mem::take(self).into_iter()
+    }
+
+    #[inline]
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        self.retain(|&type_id, value| f(type_id, &mut **value))
+    }
+}
+
+impl<'a, A: ?Sized> RawOccupiedEntry<'a, A> for btree_map::OccupiedEntry<'a, TypeId, Box<A>> {
+    #[inline]
+    fn get(&self) -> &A {
+        self.get()
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut A {
+        self.get_mut()
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut A {
+        self.into_mut()
+    }
+
+    #[inline]
+    fn insert(&mut self, value: Box<A>) -> Box<A> {
+        self.insert(value)
+    }
+
+    #[inline]
+    fn remove(self) -> Box<A> {
+        self.remove()
+    }
+}
+
+impl<'a, A: ?Sized> RawVacantEntry<'a, A> for btree_map::VacantEntry<'a, TypeId, Box<A>> {
+    #[inline]
+    fn insert(self, value: Box<A>) -> &'a mut A {
+        self.insert(value)
+    }
+}
+
+/// Storage that keeps its items in a `Vec`, in order of `TypeId`, finding them by binary search.
+///
+/// This is small and simple, and needs only `alloc`. Looking an item up takes logarithmic time,
+/// but inserting or removing one has to move all the items after it, so this suits maps that
+/// hold only a handful of items, where that costs less than hashing.
+///
+/// With neither the std nor the hashbrown feature, this is what [`Map`](crate::Map) uses by
+/// default. (If your code needs it to stay that way when some other crate enables one of those
+/// features, name it: `Map<A, SortedVec<A>>`.)
+///
+/// ```rust
+/// let mut data: anymap::Map<dyn core::any::Any, anymap::storage::SortedVec<_>> = anymap::Map::new();
+/// data.insert(42i32);
+/// data.insert("foo");
+/// assert_eq!(data.get::<i32>(), Some(&42));
+/// assert!(data.as_raw().as_slice().windows(2).all(|pair| pair[0].0 < pair[1].0));
+/// ```
+pub struct SortedVec<A: ?Sized> {
+    items: Vec<(TypeId, Box<A>)>,
+}
+
+impl<A: ?Sized> SortedVec<A> {
+    /// Creates empty storage.
+    #[inline]
+    pub fn new() -> SortedVec<A> {
+        SortedVec {
+            items: Vec::new(),
+        }
+    }
+
+    /// Returns the items, in order of `TypeId`.
+    #[inline]
+    pub fn as_slice(&self) -> &[(TypeId, Box<A>)] {
+        &self.items
+    }
+
+    /// Converts this into a `Vec` of the items, in order of `TypeId`.
+    #[inline]
+    pub fn into_vec(self) -> Vec<(TypeId, Box<A>)> {
+        self.items
+    }
+
+    /// Finds the position of the item for `type_id`, or where it would be inserted.
+    #[inline]
+    fn search(&self, type_id: TypeId) -> Result<usize, usize> {
+        self.items.binary_search_by(|(probe, _)| probe.cmp(&type_id))
+    }
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only Box<A> can.
+impl<A: ?Sized> Clone for SortedVec<A> where Box<A>: Clone {
+    #[inline]
+    fn clone(&self) -> SortedVec<A> {
+        SortedVec {
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl<A: ?Sized> Default for SortedVec<A> {
+    #[inline]
+    fn default() -> SortedVec<A> {
+        SortedVec::new()
+    }
+}
+
+impl<A: ?Sized + fmt::Debug> fmt::Debug for SortedVec<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.items.iter().map(|(type_id, value)| (type_id, value))).finish()
+    }
+}
+
+impl<A: ?Sized> IntoIterator for SortedVec<A> {
+    type Item = (TypeId, Box<A>);
+    type IntoIter = vec::IntoIter<(TypeId, Box<A>)>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+unsafe impl<A: ?Sized> RawStorage<A> for SortedVec<A> {
+    type Iter<'a> = SliceIter<'a, A> where A: 'a;
+    type IterMut<'a> = SliceIterMut<'a, A> where A: 'a;
+    type Drain<'a> = vec::Drain<'a, (TypeId, Box<A>)> where A: 'a;
+    type OccupiedEntry<'a> = SortedVecOccupiedEntry<'a, A> where A: 'a;
+    type VacantEntry<'a> = SortedVecVacantEntry<'a, A> where A: 'a;
+    type Rebind<B: ?Sized> = SortedVec<B>;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.items.clear()
+    }
+
+    #[inline]
+    fn get(&self, type_id: TypeId) -> Option<&A> {
+        self.search(type_id).ok().map(|index| &*self.items[index].1)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, type_id: TypeId) -> Option<&mut A> {
+        match self.search(type_id) {
+            Ok(index) => Some(&mut *self.items[index].1),
+            Err(_) => None,
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, type_id: TypeId, value: Box<A>) -> Option<Box<A>> {
+        match self.search(type_id) {
+            Ok(index) => Some(core::mem::replace(&mut self.items[index].1, value)),
+            Err(index) => {
+                self.items.insert(index, (type_id, value));
+                None
+            },
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, type_id: TypeId) -> Option<Box<A>> {
+        match self.search(type_id) {
+            Ok(index) => Some(self.items.remove(index).1),
+            Err(_) => None,
+        }
+    }
+
+    #[inline]
+    fn entry(&mut self, type_id: TypeId)
+        -> RawEntry<Self::OccupiedEntry<'_>, Self::VacantEntry<'_>>
+    {
+        match self.search(type_id) {
+            Ok(index) => RawEntry::Occupied(SortedVecOccupiedEntry {
+                items: &mut self.items,
+                index,
+            }),
+            Err(index) => RawEntry::Vacant(SortedVecVacantEntry {
+                items: &mut self.items,
+                index,
+                type_id,
+            }),
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        SliceIter::new(&self.items)
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        SliceIterMut::new(&mut self.items)
+    }
+
+    #[inline]
+    fn drain(&mut self) -> Self::Drain<'_> {
+        self.items.drain(..)
+    }
+
+    #[inline]
+    fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, mut f: F) {
+        self.items.retain_mut(|(type_id, value)| f(*type_id, &mut **value))
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional)
+    }
+}
+
+impl<A: ?Sized> RawCapacity<A> for SortedVec<A> {
+    #[inline]
+    fn with_capacity(capacity: usize) -> SortedVec<A> {
+        SortedVec {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit()
+    }
+}
+
+/// A view into an occupied place in a [`SortedVec`].
+pub struct SortedVecOccupiedEntry<'a, A: ?Sized> {
+    items: &'a mut Vec<(TypeId, Box<A>)>,
+    index: usize,
+}
+
+/// A view into a vacant place in a [`SortedVec`].
+pub struct SortedVecVacantEntry<'a, A: ?Sized> {
+    items: &'a mut Vec<(TypeId, Box<A>)>,
+    index: usize,
+    type_id: TypeId,
+}
+
+impl<'a, A: ?Sized> RawOccupiedEntry<'a, A> for SortedVecOccupiedEntry<'a, A> {
+    #[inline]
+    fn get(&self) -> &A {
+        &self.items[self.index].1
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut A {
+        &mut self.items[self.index].1
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut A {
+        &mut self.items[self.index].1
+    }
+
+    #[inline]
+    fn insert(&mut self, value: Box<A>) -> Box<A> {
+        core::mem::replace(&mut self.items[self.index].1, value)
+    }
+
+    #[inline]
+    fn remove(self) -> Box<A> {
+        self.items.remove(self.index).1
+    }
+}
+
+impl<'a, A: ?Sized> RawVacantEntry<'a, A> for SortedVecVacantEntry<'a, A> {
+    #[inline]
+    fn insert(self, value: Box<A>) -> &'a mut A {
+        self.items.insert(self.index, (self.type_id, value));
+        &mut self.items[self.index].1
+    }
+}
+
+/// An iterator over items kept in a slice, as in a [`SortedVec`].
+pub struct SliceIter<'a, A: ?Sized> {
+    inner: slice::Iter<'a, (TypeId, Box<A>)>,
+}
+
+impl<'a, A: ?Sized> SliceIter<'a, A> {
+    #[inline]
+    pub(crate) fn new(items: &'a [(TypeId, Box<A>)]) -> SliceIter<'a, A> {
+        SliceIter {
+            inner: items.iter(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> Clone for SliceIter<'a, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        SliceIter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> Iterator for SliceIter<'a, A> {
+    type Item = (&'a TypeId, &'a Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a Box<A>)> {
+        self.inner.next().map(|(type_id, value)| (type_id, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized> DoubleEndedIterator for SliceIter<'a, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(&'a TypeId, &'a Box<A>)> {
+        self.inner.next_back().map(|(type_id, value)| (type_id, value))
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for SliceIter<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for SliceIter<'a, A> {}
+
+/// A mutable iterator over items kept in a slice, as in a [`SortedVec`].
+pub struct SliceIterMut<'a, A: ?Sized> {
+    inner: slice::IterMut<'a, (TypeId, Box<A>)>,
+}
+
+impl<'a, A: ?Sized> SliceIterMut<'a, A> {
+    #[inline]
+    pub(crate) fn new(items: &'a mut [(TypeId, Box<A>)]) -> SliceIterMut<'a, A> {
+        SliceIterMut {
+            inner: items.iter_mut(),
+        }
+    }
+}
+
+impl<'a, A: ?Sized> Iterator for SliceIterMut<'a, A> {
+    type Item = (&'a TypeId, &'a mut Box<A>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a TypeId, &'a mut Box<A>)> {
+        self.inner.next().map(|(type_id, value)| (&*type_id, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A: ?Sized> DoubleEndedIterator for SliceIterMut<'a, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(&'a TypeId, &'a mut Box<A>)> {
+        self.inner.next_back().map(|(type_id, value)| (&*type_id, value))
+    }
+}
+
+impl<'a, A: ?Sized> ExactSizeIterator for SliceIterMut<'a, A> {}
+impl<'a, A: ?Sized> FusedIterator for SliceIterMut<'a, A> {}
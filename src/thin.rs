@@ -0,0 +1,517 @@
+//! `ThinMap`, a `Map` that’s a single pointer and doesn’t allocate until it’s needed.
+
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::hash::Hash;
+use core::iter::{FromIterator, FusedIterator};
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+use crate::storage::{DefaultStorage, RawCapacity, RawStorage};
+use crate::{Downcast, Entry, IntoBox, Map, TypeTuple, Upcast};
+
+/// A [`Map`] behind a single pointer, which isn’t allocated until the first value is inserted.
+///
+/// This is <code>[Option]&lt;[Box]&lt;[Map]&lt;A, S&gt;&gt;&gt;</code>: it’s the size of one
+/// pointer (where `Map` is several), and an empty one is just a null pointer, so `new`,
+/// `Default` and `clone` don’t allocate, and `get`, `contains`, `remove` and the like on an
+/// empty one cost no more than checking for null. That suits structures with a rarely-used
+/// extension map in each of many values.
+///
+/// It has the API of `Map`, for the same value types (`Any`, [`CloneAny`](crate::CloneAny),
+/// [`DebugAny`](crate::DebugAny), *&c.*, with `+ Send` and/or `+ Sync`) and storage, and is
+/// `Clone` when the `Map` is (that is, for the `CloneAny` variants). [`as_map`](ThinMap::as_map)
+/// and [`map_mut`](ThinMap::map_mut) give you the `Map` itself for anything else.
+///
+/// Once allocated, the `Map` stays allocated, even when emptied by `remove` or `clear`, until
+/// [`shrink_to_fit`](ThinMap::shrink_to_fit) frees it.
+///
+/// ## Example
+///
+/// ```rust
+/// use anymap::ThinMap;
+///
+/// let mut data: ThinMap = ThinMap::new();
+/// assert_eq!(core::mem::size_of_val(&data), core::mem::size_of::<usize>());
+/// assert_eq!(data.get::<i32>(), None);
+/// assert!(!data.is_allocated());
+/// data.insert(42i32);
+/// assert!(data.is_allocated());
+/// assert_eq!(data.get(), Some(&42i32));
+/// ```
+pub struct ThinMap<A: ?Sized + Downcast = dyn Any, S: RawStorage<A> = DefaultStorage<A>> {
+    map: Option<Box<Map<A, S>>>,
+}
+
+// #[derive(Clone)] would want A to implement Clone, but in reality only the storage can.
+impl<A: ?Sized + Downcast, S: RawStorage<A> + Clone> Clone for ThinMap<A, S> {
+    #[inline]
+    fn clone(&self) -> ThinMap<A, S> {
+        ThinMap {
+            map: self.map.clone(),
+        }
+    }
+}
+
+/// Equality doesn’t depend on the order of the items, or on whether an empty map is allocated.
+impl<A: ?Sized + Downcast + PartialEq, S: RawStorage<A>> PartialEq for ThinMap<A, S> {
+    fn eq(&self, other: &ThinMap<A, S>) -> bool {
+        match (&self.map, &other.map) {
+            (Some(map), Some(other)) => map == other,
+            (Some(map), None) | (None, Some(map)) => map.is_empty(),
+            (None, None) => true,
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast + Eq, S: RawStorage<A>> Eq for ThinMap<A, S> {}
+
+/// The hash is the same as that of the equivalent `Map`.
+impl<A: ?Sized + Downcast + Hash, S: RawStorage<A>> Hash for ThinMap<A, S> {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.map.as_ref().map_or(0, |map| map.fingerprint()))
+    }
+}
+
+impl<A: ?Sized + Downcast + fmt::Debug, S: RawStorage<A>> fmt::Debug for ThinMap<A, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.map {
+            Some(ref map) => map.fmt(f),
+            None => f.debug_map().finish(),
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Default for ThinMap<A, S> {
+    #[inline]
+    fn default() -> ThinMap<A, S> {
+        ThinMap::new()
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> From<Map<A, S>> for ThinMap<A, S> {
+    /// Boxes the map, unless it’s empty.
+    #[inline]
+    fn from(map: Map<A, S>) -> ThinMap<A, S> {
+        ThinMap {
+            map: if map.is_empty() { None } else { Some(Box::new(map)) },
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawCapacity<A>> ThinMap<A, S> {
+    /// Creates an empty collection with the given initial capacity,
+    /// which only allocates if the capacity isn’t zero.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> ThinMap<A, S> {
+        ThinMap {
+            map: if capacity == 0 { None } else { Some(Box::new(Map::with_capacity(capacity))) },
+        }
+    }
+
+    /// Returns the number of elements the collection can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.capacity())
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the collection. The collection may reserve more space to avoid
+    /// frequent reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        if additional != 0 {
+            self.map_mut().reserve(additional)
+        }
+    }
+
+    /// Shrinks the capacity of the collection as much as possible, freeing it altogether
+    /// if it’s empty.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        match self.map {
+            Some(ref map) if map.is_empty() => self.map = None,
+            Some(ref mut map) => map.shrink_to_fit(),
+            None => (),
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> ThinMap<A, S> {
+    /// Create an empty collection. This doesn’t allocate.
+    #[inline]
+    pub const fn new() -> ThinMap<A, S> {
+        ThinMap { map: None }
+    }
+
+    /// Returns true if the `Map` has been allocated.
+    #[inline]
+    pub fn is_allocated(&self) -> bool {
+        self.map.is_some()
+    }
+
+    /// Returns the `Map`, if it has been allocated.
+    #[inline]
+    pub fn as_map(&self) -> Option<&Map<A, S>> {
+        self.map.as_deref()
+    }
+
+    /// Returns the `Map`, allocating it if it hasn’t been already.
+    #[inline]
+    pub fn map_mut(&mut self) -> &mut Map<A, S> {
+        self.map.get_or_insert_with(Default::default)
+    }
+
+    /// Converts this into a `Map`.
+    #[inline]
+    pub fn into_map(self) -> Map<A, S> {
+        self.map.map_or_else(Map::new, |map| *map)
+    }
+
+    /// Returns the number of items in the collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len())
+    }
+
+    /// Returns true if there are no items in the collection.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.as_ref().is_none_or(|map| map.is_empty())
+    }
+
+    /// Removes all items from the collection. Keeps the allocated memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        if let Some(ref mut map) = self.map {
+            map.clear()
+        }
+    }
+
+    /// Returns a reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.map.as_ref()?.get()
+    }
+
+    /// Returns a mutable reference to the value stored in the collection for the type `T`,
+    /// if it exists.
+    #[inline]
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.map.as_mut()?.get_mut()
+    }
+
+    /// Returns references to the values stored in the collection for each of the types in
+    /// the tuple `T`, if they all exist. See [`Map::get_all`].
+    #[inline]
+    pub fn get_all<T: TypeTuple<A>>(&self) -> Option<T::Refs<'_>> {
+        self.map.as_ref()?.get_all::<T>()
+    }
+
+    /// Returns mutable references to the values stored in the collection for each of the
+    /// types in the tuple `T`, if they all exist. See [`Map::get_many_mut`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn get_many_mut<T: TypeTuple<A>>(&mut self) -> Option<T::RefsMut<'_>> {
+        self.map.as_mut()?.get_many_mut::<T>()
+    }
+
+    /// Sets the value stored in the collection for the type `T`.
+    /// If the collection already had a value of type `T`, that value is returned.
+    /// Otherwise, `None` is returned.
+    #[inline]
+    pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        self.map_mut().insert(value)
+    }
+
+    /// Sets the values stored in the collection for each of the types in the tuple,
+    /// returning the values they replace. See [`Map::insert_all`].
+    #[inline]
+    pub fn insert_all<T: TypeTuple<A>>(&mut self, values: T) -> T::Options {
+        self.map_mut().insert_all(values)
+    }
+
+    /// Removes the `T` value from the collection,
+    /// returning it if there was one or `None` if there was not.
+    #[inline]
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        self.map.as_mut()?.remove()
+    }
+
+    /// Removes the values of each of the types in the tuple `T` from the collection,
+    /// returning them if they were all there. See [`Map::remove_all`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in `T`.
+    #[inline]
+    pub fn remove_all<T: TypeTuple<A>>(&mut self) -> Option<T> {
+        self.map.as_mut()?.remove_all::<T>()
+    }
+
+    /// Returns true if the collection contains a value of each of the types in the tuple.
+    #[inline]
+    pub fn contains_all<T: TypeTuple<A>>(&self) -> bool {
+        self.map.as_ref().is_some_and(|map| map.contains_all::<T>())
+    }
+
+    /// Returns true if the collection contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.map.as_ref().is_some_and(|map| map.contains::<T>())
+    }
+
+    /// Gets the entry for the given type in the collection for in-place manipulation.
+    ///
+    /// This allocates the `Map` if it hasn’t been already.
+    #[inline]
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T, S> {
+        self.map_mut().entry()
+    }
+
+    /// Returns the name of the type stored in the collection under `type_id`, if there is
+    /// such an item and its name is known. See [`Map::type_name`].
+    #[inline]
+    pub fn type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.map.as_ref()?.type_name(type_id)
+    }
+
+    /// An iterator visiting the names of the types in the collection, as far as they are
+    /// known. See [`Map::type_names`].
+    #[inline]
+    pub fn type_names(&self) -> ThinTypeNames<'_, A, S> {
+        ThinTypeNames(self.map.as_ref().map(|map| map.type_names()))
+    }
+
+    /// An iterator visiting all items in the collection, with their `TypeId`s.
+    #[inline]
+    pub fn iter(&self) -> ThinIter<'_, A, S> {
+        ThinIter(self.map.as_ref().map(|map| map.iter()))
+    }
+
+    /// An iterator visiting all items in the collection, with their `TypeId`s,
+    /// with mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> ThinIterMut<'_, A, S> {
+        ThinIterMut(self.map.as_mut().map(|map| map.iter_mut()))
+    }
+
+    /// Clears the collection, returning all the values as an iterator.
+    /// Keeps the allocated memory for reuse.
+    #[inline]
+    pub fn drain(&mut self) -> ThinDrain<'_, A, S> {
+        ThinDrain(self.map.as_mut().map(|map| map.drain()))
+    }
+
+    /// Retains only the items for which `f` returns true.
+    #[inline]
+    pub fn retain<F: FnMut(TypeId, &mut A) -> bool>(&mut self, f: F) {
+        if let Some(ref mut map) = self.map {
+            map.retain(f)
+        }
+    }
+
+    /// Converts this into a map with a weaker value type. See [`Map::upcast`].
+    #[inline]
+    pub fn upcast<B: ?Sized + Downcast>(self) -> ThinMap<B, S::Rebind<B>> where A: Upcast<B> {
+        ThinMap {
+            map: self.map.map(|map| Box::new(map.upcast())),
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Extend<Box<A>> for ThinMap<A, S> {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Box<A>>>(&mut self, iter: T) {
+        let mut iter = iter.into_iter().peekable();
+        if iter.peek().is_some() {
+            self.map_mut().extend(iter)
+        }
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> FromIterator<Box<A>> for ThinMap<A, S> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Box<A>>>(iter: T) -> ThinMap<A, S> {
+        let mut map = ThinMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for &'a ThinMap<A, S> {
+    type Item = (TypeId, &'a A);
+    type IntoIter = ThinIter<'a, A, S>;
+
+    #[inline]
+    fn into_iter(self) -> ThinIter<'a, A, S> {
+        self.iter()
+    }
+}
+
+impl<'a, A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for &'a mut ThinMap<A, S> {
+    type Item = (TypeId, &'a mut A);
+    type IntoIter = ThinIterMut<'a, A, S>;
+
+    #[inline]
+    fn into_iter(self) -> ThinIterMut<'a, A, S> {
+        self.iter_mut()
+    }
+}
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> IntoIterator for ThinMap<A, S> {
+    type Item = Box<A>;
+    type IntoIter = ThinIntoIter<A, S>;
+
+    #[inline]
+    fn into_iter(self) -> ThinIntoIter<A, S> {
+        ThinIntoIter(self.map.map(|map| map.into_iter()))
+    }
+}
+
+/// Defines an iterator for `ThinMap` that wraps the `Map` one, if the map is allocated.
+macro_rules! thin_iterator {
+    ($(#[$attr:meta])* $name:ident $(<$lt:lifetime>)?: $inner:ident -> $item:ty) => {
+        $(#[$attr])*
+        pub struct $name<
+            $($lt,)?
+            A: ?Sized + Downcast $(+ $lt)?,
+            S: RawStorage<A> $(+ $lt)? = DefaultStorage<A>,
+        >(Option<crate::$inner<$($lt,)? A, S>>);
+
+        impl<$($lt,)? A: ?Sized + Downcast, S: RawStorage<A>> Clone for $name<$($lt,)? A, S>
+        where crate::$inner<$($lt,)? A, S>: Clone {
+            #[inline]
+            fn clone(&self) -> Self {
+                $name(self.0.clone())
+            }
+        }
+
+        impl<$($lt,)? A: ?Sized + Downcast, S: RawStorage<A>> Iterator for $name<$($lt,)? A, S> {
+            type Item = $item;
+
+            #[inline]
+            fn next(&mut self) -> Option<$item> {
+                self.0.as_mut()?.next()
+            }
+
+            #[inline]
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.as_ref().map_or((0, Some(0)), |inner| inner.size_hint())
+            }
+        }
+
+        impl<$($lt,)? A: ?Sized + Downcast, S: RawStorage<A>> DoubleEndedIterator
+        for $name<$($lt,)? A, S>
+        where crate::$inner<$($lt,)? A, S>: DoubleEndedIterator<Item = $item> {
+            #[inline]
+            fn next_back(&mut self) -> Option<$item> {
+                self.0.as_mut()?.next_back()
+            }
+        }
+
+        impl<$($lt,)? A: ?Sized + Downcast, S: RawStorage<A>> ExactSizeIterator
+        for $name<$($lt,)? A, S>
+        where crate::$inner<$($lt,)? A, S>: ExactSizeIterator {}
+
+        impl<$($lt,)? A: ?Sized + Downcast, S: RawStorage<A>> FusedIterator
+        for $name<$($lt,)? A, S>
+        where crate::$inner<$($lt,)? A, S>: FusedIterator {}
+    };
+}
+
+thin_iterator! {
+    /// `ThinMap::type_names` iterator.
+    ThinTypeNames<'a>: TypeNames -> &'static str
+}
+
+thin_iterator! {
+    /// `ThinMap::iter` iterator.
+    ThinIter<'a>: Iter -> (TypeId, &'a A)
+}
+
+thin_iterator! {
+    /// `ThinMap::iter_mut` iterator.
+    ThinIterMut<'a>: IterMut -> (TypeId, &'a mut A)
+}
+
+thin_iterator! {
+    /// `ThinMap::drain` iterator.
+    ThinDrain<'a>: Drain -> Box<A>
+}
+
+thin_iterator! {
+    /// `ThinMap::into_iter` iterator.
+    ThinIntoIter: IntoIter -> Box<A>
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem::size_of;
+    use crate::{CloneAny, CloneHashAny};
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec::Vec};
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct A(i32);
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)] struct B(i32);
+
+    #[test]
+    fn test_thin() {
+        assert_eq!(size_of::<ThinMap>(), size_of::<usize>());
+        assert_eq!(size_of::<ThinMap<dyn CloneAny + Send>>(), size_of::<usize>());
+
+        let mut map: ThinMap<dyn CloneAny> = ThinMap::new();
+        assert_eq!(map.get::<A>(), None);
+        assert_eq!(map.remove::<A>(), None);
+        assert!(!map.contains::<A>());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.drain().len(), 0);
+        map.clear();
+        map.extend(None);
+        assert!(!map.is_allocated());
+        assert_eq!(format!("{:?}", map.clone()), "{}");
+
+        assert_eq!(map.insert(A(1)), None);
+        assert!(map.is_allocated());
+        *map.entry::<B>().or_insert(B(0)) = B(2);
+        assert_eq!(map.get_all::<(A, B)>(), Some((&A(1), &B(2))));
+        let map2 = map.clone();
+        assert_eq!(map2.len(), 2);
+        assert_eq!(map.remove::<A>(), Some(A(1)));
+        assert_eq!(map.into_iter().count(), 1);
+        assert_eq!(map2.get::<A>(), Some(&A(1)));
+        assert_eq!(map2.type_names().count(), 2);
+
+        let mut map = map2.upcast::<dyn Any>();
+        map.clear();
+        assert!(map.is_allocated());
+        map.shrink_to_fit();
+        assert!(!map.is_allocated());
+    }
+
+    #[test]
+    fn test_thin_eq() {
+        let mut map: ThinMap<dyn CloneHashAny> = ThinMap::new();
+        let mut map2: ThinMap<dyn CloneHashAny> = ThinMap::new();
+        let _ = map2.insert(A(1));
+        assert_ne!(map, map2);
+        let _ = map2.remove::<A>();
+        assert_eq!(map, map2);
+        let _ = map.insert(B(2));
+        let _ = map2.insert(B(2));
+        assert_eq!(map, map2);
+        let collected = map.clone().into_iter().collect::<Vec<_>>();
+        assert_eq!(collected.into_iter().collect::<ThinMap<dyn CloneHashAny>>(), map);
+    }
+}
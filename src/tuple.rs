@@ -0,0 +1,166 @@
+use core::any::TypeId;
+
+use crate::any::{Downcast, IntoBox};
+use crate::storage::RawStorage;
+use crate::Map;
+
+/// Keeps `TypeTuple` from being implemented outside this crate, since its methods are unsafe
+/// internals of `Map`.
+mod private {
+    use crate::{Downcast, IntoBox};
+
+    pub trait Sealed {}
+
+    /// A collection that a `TypeTuple` can insert its values into and remove them from.
+    pub trait Target<A: ?Sized + Downcast> {
+        fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T>;
+        fn remove<T: IntoBox<A>>(&mut self) -> Option<T>;
+    }
+}
+
+pub(crate) use self::private::Target;
+
+impl<A: ?Sized + Downcast, S: RawStorage<A>> Target<A> for Map<A, S> {
+    #[inline]
+    fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        Map::insert(self, value)
+    }
+
+    #[inline]
+    fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        Map::remove(self)
+    }
+}
+
+/// A tuple of types that can be stored in a `Map<A>`, for working with several types at once.
+///
+/// This is implemented for tuples of up to twelve types, each implementing `IntoBox<A>`. It’s
+/// sealed: it can’t be implemented for other types.
+pub trait TypeTuple<A: ?Sized + Downcast>: Sized + private::Sealed {
+    /// The number of types in the tuple.
+    const LEN: usize;
+
+    /// A tuple of references to values of each of the types.
+    type Refs<'a> where A: 'a;
+
+    /// A tuple of mutable references to values of each of the types.
+    type RefsMut<'a> where A: 'a;
+
+    /// A tuple of an `Option` of each of the types.
+    type Options;
+
+    /// Inserts each value into `map`, returning the values they replace.
+    #[doc(hidden)]
+    fn insert_all<M: Target<A>>(self, map: &mut M) -> Self::Options;
+
+    /// Gets references to values of each of the types, given a function that finds the value for
+    /// a given `TypeId`, if any.
+    ///
+    /// # Safety
+    ///
+    /// The values `get` returns must be of the type the `TypeId` is for.
+    #[doc(hidden)]
+    unsafe fn get_all<'a, F>(get: F) -> Option<Self::Refs<'a>>
+        where F: FnMut(TypeId) -> Option<&'a A>, A: 'a;
+
+    /// Gets mutable references to values of each of the types, given a function that finds the
+    /// value for a given `TypeId`, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in the tuple.
+    ///
+    /// # Safety
+    ///
+    /// The pointers `get` returns must point to values of the type the `TypeId` is for, and be
+    /// valid for `'a`; the values must not be accessed by other means during `'a`.
+    #[doc(hidden)]
+    unsafe fn get_many_mut<'a, F>(get: F) -> Option<Self::RefsMut<'a>>
+        where F: FnMut(TypeId) -> Option<*mut A>, A: 'a;
+
+    /// Removes values of each of the types from `map`, stopping at the first that’s missing (so
+    /// callers should check `contains_all` first).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same type appears more than once in the tuple.
+    #[doc(hidden)]
+    fn remove_all<M: Target<A>>(map: &mut M) -> Option<Self>;
+
+    /// Returns true if `contains` returns true for each of the types’ `TypeId`s.
+    #[doc(hidden)]
+    fn contains_all<F>(contains: F) -> bool
+        where F: FnMut(TypeId) -> bool;
+}
+
+/// Panics if any type appears twice, because that would mean handing out aliasing references.
+#[inline]
+fn assert_distinct(type_ids: &[TypeId]) {
+    for (i, type_id) in type_ids.iter().enumerate() {
+        assert!(!type_ids[..i].contains(type_id), "the same type appears twice in the tuple");
+    }
+}
+
+macro_rules! impl_type_tuple {
+    ($len:expr; $($T:ident)+) => {
+        impl<$($T),+> private::Sealed for ($($T,)+) {}
+
+        impl<A: ?Sized + Downcast, $($T: IntoBox<A>),+> TypeTuple<A> for ($($T,)+) {
+            const LEN: usize = $len;
+
+            type Refs<'a> = ($(&'a $T,)+) where A: 'a;
+
+            type RefsMut<'a> = ($(&'a mut $T,)+) where A: 'a;
+
+            type Options = ($(Option<$T>,)+);
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn insert_all<M: Target<A>>(self, map: &mut M) -> Self::Options {
+                let ($($T,)+) = self;
+                ($(map.insert($T),)+)
+            }
+
+            #[inline]
+            unsafe fn get_all<'a, F>(mut get: F) -> Option<Self::Refs<'a>>
+                where F: FnMut(TypeId) -> Option<&'a A>, A: 'a
+            {
+                Some(($(get(TypeId::of::<$T>())?.downcast_ref_unchecked::<$T>(),)+))
+            }
+
+            #[inline]
+            unsafe fn get_many_mut<'a, F>(mut get: F) -> Option<Self::RefsMut<'a>>
+                where F: FnMut(TypeId) -> Option<*mut A>, A: 'a
+            {
+                assert_distinct(&[$(TypeId::of::<$T>()),+]);
+                Some(($((*get(TypeId::of::<$T>())?).downcast_mut_unchecked::<$T>(),)+))
+            }
+
+            #[inline]
+            fn remove_all<M: Target<A>>(map: &mut M) -> Option<Self> {
+                assert_distinct(&[$(TypeId::of::<$T>()),+]);
+                Some(($(map.remove::<$T>()?,)+))
+            }
+
+            #[inline]
+            fn contains_all<F>(mut contains: F) -> bool
+                where F: FnMut(TypeId) -> bool
+            {
+                $(contains(TypeId::of::<$T>()))&&+
+            }
+        }
+    }
+}
+
+impl_type_tuple!(1; T1);
+impl_type_tuple!(2; T1 T2);
+impl_type_tuple!(3; T1 T2 T3);
+impl_type_tuple!(4; T1 T2 T3 T4);
+impl_type_tuple!(5; T1 T2 T3 T4 T5);
+impl_type_tuple!(6; T1 T2 T3 T4 T5 T6);
+impl_type_tuple!(7; T1 T2 T3 T4 T5 T6 T7);
+impl_type_tuple!(8; T1 T2 T3 T4 T5 T6 T7 T8);
+impl_type_tuple!(9; T1 T2 T3 T4 T5 T6 T7 T8 T9);
+impl_type_tuple!(10; T1 T2 T3 T4 T5 T6 T7 T8 T9 T10);
+impl_type_tuple!(11; T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11);
+impl_type_tuple!(12; T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12);
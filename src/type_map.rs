@@ -0,0 +1,414 @@
+use core::any::Any;
+
+/// A marker type serving as the key for a value in a `TypeMap`.
+///
+/// This lets one map hold several values of the same type, under different keys:
+///
+/// ```rust
+/// struct UserId;
+/// impl anymap::Key for UserId {
+///     type Value = String;
+/// }
+///
+/// struct TenantId;
+/// impl anymap::Key for TenantId {
+///     type Value = String;
+/// }
+/// ```
+pub trait Key: 'static {
+    /// The type of the value stored under this key.
+    type Value: Any;
+}
+
+/// Generates `TypeMap` and its entry types, in the same manner as `everything!`.
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+macro_rules! type_map {
+    ($example_init:literal $(, $entry_generics:ty)?) => {
+        /// A collection containing zero or one values for any given [`Key`], with values of the
+        /// type the key specifies, and allowing convenient, type-safe access to those values.
+        ///
+        /// Where a [`Map`] is keyed by the type of the value, this is keyed by a separate marker
+        /// type, so that it can hold several values of the same type. The value type `A` is as
+        /// for [`Map`], and must be satisfied by the keys’ `Value` types. (The keys, being marker
+        /// types with no values to name them, appear in the `Debug` output as `TypeId`s.)
+        ///
+        /// ```rust
+        /// use anymap::Key;
+        ///
+        /// struct UserId;
+        /// impl Key for UserId {
+        ///     type Value = String;
+        /// }
+        ///
+        /// struct TenantId;
+        /// impl Key for TenantId {
+        ///     type Value = String;
+        /// }
+        ///
+        #[doc = $example_init]
+        /// data.insert::<UserId>("alice".to_owned());
+        /// data.insert::<TenantId>("acme".to_owned());
+        /// assert_eq!(data.get::<UserId>().map(|id| &**id), Some("alice"));
+        /// assert_eq!(data.get::<TenantId>().map(|id| &**id), Some("acme"));
+        /// data.entry::<TenantId>().or_default().push_str("-corp");
+        /// assert_eq!(data.remove::<TenantId>().as_deref(), Some("acme-corp"));
+        /// assert!(!data.contains::<TenantId>());
+        /// ```
+        pub struct TypeMap<A: ?Sized + Downcast = dyn Any> {
+            /// Maps `TypeId::of::<K>()` to a `K::Value`.
+            raw: RawMap<A>,
+        }
+
+        impl<A: ?Sized + Downcast> Clone for TypeMap<A> where Box<A>: Clone {
+            #[inline]
+            fn clone(&self) -> TypeMap<A> {
+                TypeMap {
+                    raw: self.raw.clone(),
+                }
+            }
+        }
+
+        impl<A: ?Sized + Downcast + fmt::Debug> fmt::Debug for TypeMap<A> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.raw.iter().map(|(&type_id, value)| {
+                        (crate::TypeName::new(type_id, None), value)
+                    }))
+                    .finish()
+            }
+        }
+
+        impl<A: ?Sized + Downcast> Default for TypeMap<A> {
+            #[inline]
+            fn default() -> TypeMap<A> {
+                TypeMap::new()
+            }
+        }
+
+        impl<A: ?Sized + Downcast> TypeMap<A> {
+            /// Create an empty collection.
+            #[inline]
+            pub fn new() -> TypeMap<A> {
+                TypeMap {
+                    raw: RawMap::with_hasher(Default::default()),
+                }
+            }
+
+            /// Creates an empty collection with the given initial capacity.
+            #[inline]
+            pub fn with_capacity(capacity: usize) -> TypeMap<A> {
+                TypeMap {
+                    raw: RawMap::with_capacity_and_hasher(capacity, Default::default()),
+                }
+            }
+
+            /// Returns the number of elements the collection can hold without reallocating.
+            #[inline]
+            pub fn capacity(&self) -> usize {
+                self.raw.capacity()
+            }
+
+            /// Reserves capacity for at least `additional` more elements to be inserted
+            /// in the collection.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the new allocation size overflows `usize`.
+            #[inline]
+            pub fn reserve(&mut self, additional: usize) {
+                self.raw.reserve(additional)
+            }
+
+            /// Shrinks the capacity of the collection as much as possible.
+            #[inline]
+            pub fn shrink_to_fit(&mut self) {
+                self.raw.shrink_to_fit()
+            }
+
+            /// Returns the number of items in the collection.
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.raw.len()
+            }
+
+            /// Returns true if there are no items in the collection.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.raw.is_empty()
+            }
+
+            /// Removes all items from the collection. Keeps the allocated memory for reuse.
+            #[inline]
+            pub fn clear(&mut self) {
+                self.raw.clear()
+            }
+
+            /// Returns a reference to the value stored in the collection for the key `K`,
+            /// if it exists.
+            #[inline]
+            pub fn get<K: Key>(&self) -> Option<&K::Value> where K::Value: IntoBox<A> {
+                self.raw.get(&TypeId::of::<K>())
+                    .map(|any| unsafe { any.downcast_ref_unchecked::<K::Value>() })
+            }
+
+            /// Returns a mutable reference to the value stored in the collection for the key `K`,
+            /// if it exists.
+            #[inline]
+            pub fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> where K::Value: IntoBox<A> {
+                self.raw.get_mut(&TypeId::of::<K>())
+                    .map(|any| unsafe { any.downcast_mut_unchecked::<K::Value>() })
+            }
+
+            /// Sets the value stored in the collection for the key `K`.
+            /// If the collection already had a value for `K`, that value is returned.
+            /// Otherwise, `None` is returned.
+            #[inline]
+            pub fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value>
+                where K::Value: IntoBox<A>
+            {
+                self.raw.insert(TypeId::of::<K>(), value.into_box())
+                    .map(|any| unsafe { *any.downcast_unchecked::<K::Value>() })
+            }
+
+            /// Removes the value for the key `K` from the collection,
+            /// returning it if there was one or `None` if there was not.
+            #[inline]
+            pub fn remove<K: Key>(&mut self) -> Option<K::Value> where K::Value: IntoBox<A> {
+                self.raw.remove(&TypeId::of::<K>())
+                    .map(|any| *unsafe { any.downcast_unchecked::<K::Value>() })
+            }
+
+            /// Returns true if the collection contains a value for the key `K`.
+            #[inline]
+            pub fn contains<K: Key>(&self) -> bool {
+                self.raw.contains_key(&TypeId::of::<K>())
+            }
+
+            /// Gets the entry for the given key in the collection for in-place manipulation
+            #[inline]
+            pub fn entry<K: Key>(&mut self) -> TypeMapEntry<'_, A, K> where K::Value: IntoBox<A> {
+                match self.raw.entry(TypeId::of::<K>()) {
+                    hash_map::Entry::Occupied(e) => TypeMapEntry::Occupied(OccupiedTypeMapEntry {
+                        inner: e,
+                        type_: PhantomData,
+                    }),
+                    hash_map::Entry::Vacant(e) => TypeMapEntry::Vacant(VacantTypeMapEntry {
+                        inner: e,
+                        type_: PhantomData,
+                    }),
+                }
+            }
+
+            /// Get access to the raw hash map that backs this.
+            #[inline]
+            pub fn as_raw(&self) -> &RawMap<A> {
+                &self.raw
+            }
+
+            /// Get mutable access to the raw hash map that backs this.
+            ///
+            /// # Safety
+            ///
+            /// If you insert any values to the raw map, the key (a `TypeId`) must match the
+            /// value’s type as specified by a `Key`, or *undefined behaviour* will occur when you
+            /// access that entry.
+            #[inline]
+            pub unsafe fn as_raw_mut(&mut self) -> &mut RawMap<A> {
+                &mut self.raw
+            }
+        }
+
+        /// A view into a single occupied location in a `TypeMap`.
+        pub struct OccupiedTypeMapEntry<'a, A: ?Sized + Downcast, K: 'a> {
+            inner: hash_map::OccupiedEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
+            type_: PhantomData<K>,
+        }
+
+        /// A view into a single empty location in a `TypeMap`.
+        pub struct VacantTypeMapEntry<'a, A: ?Sized + Downcast, K: 'a> {
+            inner: hash_map::VacantEntry<'a, TypeId, Box<A>, $($entry_generics)?>,
+            type_: PhantomData<K>,
+        }
+
+        /// A view into a single location in a `TypeMap`, which may be vacant or occupied.
+        pub enum TypeMapEntry<'a, A: ?Sized + Downcast, K: 'a> {
+            /// An occupied Entry
+            Occupied(OccupiedTypeMapEntry<'a, A, K>),
+            /// A vacant Entry
+            Vacant(VacantTypeMapEntry<'a, A, K>),
+        }
+
+        impl<'a, A: ?Sized + Downcast, K: Key> TypeMapEntry<'a, A, K> where K::Value: IntoBox<A> {
+            /// Ensures a value is in the entry by inserting the default if empty, and returns
+            /// a mutable reference to the value in the entry.
+            #[inline]
+            pub fn or_insert(self, default: K::Value) -> &'a mut K::Value {
+                match self {
+                    TypeMapEntry::Occupied(inner) => inner.into_mut(),
+                    TypeMapEntry::Vacant(inner) => inner.insert(default),
+                }
+            }
+
+            /// Ensures a value is in the entry by inserting the result of the default function if
+            /// empty, and returns a mutable reference to the value in the entry.
+            #[inline]
+            pub fn or_insert_with<F: FnOnce() -> K::Value>(self, default: F) -> &'a mut K::Value {
+                match self {
+                    TypeMapEntry::Occupied(inner) => inner.into_mut(),
+                    TypeMapEntry::Vacant(inner) => inner.insert(default()),
+                }
+            }
+
+            /// Ensures a value is in the entry by inserting the default value if empty,
+            /// and returns a mutable reference to the value in the entry.
+            #[inline]
+            pub fn or_default(self) -> &'a mut K::Value where K::Value: Default {
+                match self {
+                    TypeMapEntry::Occupied(inner) => inner.into_mut(),
+                    TypeMapEntry::Vacant(inner) => inner.insert(Default::default()),
+                }
+            }
+
+            /// Provides in-place mutable access to an occupied entry before any potential inserts
+            /// into the map.
+            #[inline]
+            pub fn and_modify<F: FnOnce(&mut K::Value)>(self, f: F) -> Self {
+                match self {
+                    TypeMapEntry::Occupied(mut inner) => {
+                        f(inner.get_mut());
+                        TypeMapEntry::Occupied(inner)
+                    },
+                    TypeMapEntry::Vacant(inner) => TypeMapEntry::Vacant(inner),
+                }
+            }
+        }
+
+        impl<'a, A: ?Sized + Downcast, K: Key> OccupiedTypeMapEntry<'a, A, K>
+            where K::Value: IntoBox<A>
+        {
+            /// Gets a reference to the value in the entry
+            #[inline]
+            pub fn get(&self) -> &K::Value {
+                unsafe { self.inner.get().downcast_ref_unchecked() }
+            }
+
+            /// Gets a mutable reference to the value in the entry
+            #[inline]
+            pub fn get_mut(&mut self) -> &mut K::Value {
+                unsafe { self.inner.get_mut().downcast_mut_unchecked() }
+            }
+
+            /// Converts the entry into a mutable reference to the value in the entry
+            /// with a lifetime bound to the collection itself
+            #[inline]
+            pub fn into_mut(self) -> &'a mut K::Value {
+                unsafe { self.inner.into_mut().downcast_mut_unchecked() }
+            }
+
+            /// Sets the value of the entry, and returns the entry's old value
+            #[inline]
+            pub fn insert(&mut self, value: K::Value) -> K::Value {
+                unsafe { *self.inner.insert(value.into_box()).downcast_unchecked() }
+            }
+
+            /// Takes the value out of the entry, and returns it
+            #[inline]
+            pub fn remove(self) -> K::Value {
+                unsafe { *self.inner.remove().downcast_unchecked() }
+            }
+        }
+
+        impl<'a, A: ?Sized + Downcast, K: Key> VacantTypeMapEntry<'a, A, K>
+            where K::Value: IntoBox<A>
+        {
+            /// Sets the value of the entry with the entry's key,
+            /// and returns a mutable reference to it
+            #[inline]
+            pub fn insert(self, value: K::Value) -> &'a mut K::Value {
+                unsafe { self.inner.insert(value.into_box()).downcast_mut_unchecked() }
+            }
+        }
+
+        #[cfg(test)]
+        mod type_map_tests {
+            use crate::{CloneAny, DebugAny, Key};
+            use super::*;
+            #[cfg(not(feature = "std"))]
+            use alloc::{format, string::{String, ToString}};
+
+            struct UserId;
+            impl Key for UserId {
+                type Value = String;
+            }
+
+            struct TenantId;
+            impl Key for TenantId {
+                type Value = String;
+            }
+
+            struct Count;
+            impl Key for Count {
+                type Value = u32;
+            }
+
+            #[test]
+            fn test_same_value_type() {
+                let mut map = TypeMap::<dyn CloneAny>::new();
+                assert_eq!(map.insert::<UserId>("alice".to_string()), None);
+                assert_eq!(map.insert::<TenantId>("acme".to_string()), None);
+                assert_eq!(map.insert::<Count>(1), None);
+                assert_eq!(map.len(), 3);
+                assert_eq!(map.get::<UserId>().map(|s| &**s), Some("alice"));
+                assert_eq!(map.get::<TenantId>().map(|s| &**s), Some("acme"));
+                *map.get_mut::<Count>().unwrap() += 1;
+
+                let clone = map.clone();
+                assert_eq!(map.insert::<UserId>("bob".to_string()).as_deref(), Some("alice"));
+                assert_eq!(map.remove::<TenantId>().as_deref(), Some("acme"));
+                assert!(!map.contains::<TenantId>());
+                assert_eq!(clone.get::<UserId>().map(|s| &**s), Some("alice"));
+                assert_eq!(clone.get::<TenantId>().map(|s| &**s), Some("acme"));
+                assert_eq!(clone.get::<Count>(), Some(&2));
+                assert_eq!(map.get::<UserId>().map(|s| &**s), Some("bob"));
+            }
+
+            #[test]
+            fn test_entry() {
+                let mut map = TypeMap::<dyn Any>::new();
+                assert_eq!(*map.entry::<Count>().or_insert(10), 10);
+                assert_eq!(*map.entry::<Count>().and_modify(|c| *c += 1).or_insert(0), 11);
+                assert_eq!(map.entry::<UserId>().or_insert_with(|| "carol".to_string()), "carol");
+                assert_eq!(map.entry::<TenantId>().or_default(), "");
+
+                match map.entry::<UserId>() {
+                    TypeMapEntry::Vacant(_) => unreachable!(),
+                    TypeMapEntry::Occupied(mut view) => {
+                        assert_eq!(view.get(), "carol");
+                        view.get_mut().push('!');
+                        assert_eq!(view.insert("dave".to_string()), "carol!");
+                        assert_eq!(view.remove(), "dave");
+                    },
+                }
+                match map.entry::<UserId>() {
+                    TypeMapEntry::Occupied(_) => unreachable!(),
+                    TypeMapEntry::Vacant(view) => {
+                        assert_eq!(view.insert("erin".to_string()), "erin");
+                    },
+                }
+                assert_eq!(map.get::<UserId>().map(|s| &**s), Some("erin"));
+                assert_eq!(map.len(), 3);
+            }
+
+            #[test]
+            fn test_debug() {
+                let mut map = TypeMap::<dyn DebugAny>::new();
+                let _ = map.insert::<Count>(1);
+                *map.entry::<UserId>().or_default() += "alice";
+                let debug = format!("{:?}", map);
+                // The keys are marker types, with no values to name them, so they show as `TypeId`s.
+                assert!(debug.contains(&format!("{:?}: 1", TypeId::of::<Count>())), "{}", debug);
+                assert!(debug.contains(&format!("{:?}: \"alice\"", TypeId::of::<UserId>())), "{}", debug);
+            }
+        }
+    };
+}